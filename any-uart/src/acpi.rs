@@ -0,0 +1,496 @@
+use core::ptr::NonNull;
+
+use crate::{Config, FnPhysToVirt, IoKind, Parity, StopBits, Uart, of::of_find};
+
+const HEADER_LEN: usize = 36;
+
+const SPACE_MEMORY: u8 = 0;
+const SPACE_IO: u8 = 1;
+
+// DBG2 serial port subtypes, also used as the SPCR interface type.
+const SUBTYPE_16550: u16 = 0x00;
+const SUBTYPE_16550_SUBSET: u16 = 0x01;
+const SUBTYPE_PL011: u16 = 0x03;
+const SUBTYPE_16550_NVIDIA: u16 = 0x05;
+const SUBTYPE_SBSA_32BIT: u16 = 0x0d;
+const SUBTYPE_SBSA_GENERIC: u16 = 0x0e;
+const SUBTYPE_BCM2835: u16 = 0x10;
+const SUBTYPE_16550_GAS: u16 = 0x12;
+
+/// ACPI Generic Address Structure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GenericAddress {
+    /// `0` for system memory, `1` for system I/O.
+    pub space_id: u8,
+    pub bit_width: u8,
+    pub bit_offset: u8,
+    /// `1` byte, `2` word, `3` dword, `4` qword, `0` undefined.
+    pub access_size: u8,
+    pub address: u64,
+}
+
+impl GenericAddress {
+    fn parse(bytes: &[u8]) -> Option<Self> {
+        let b = bytes.get(..12)?;
+        Some(Self {
+            space_id: b[0],
+            bit_width: b[1],
+            bit_offset: b[2],
+            access_size: b[3],
+            address: u64::from_le_bytes(b[4..].try_into().ok()?),
+        })
+    }
+
+    /// Access kind of a 16550 at this address, picked like Linux does.
+    fn io_kind(&self) -> IoKind {
+        match (self.space_id, self.access_size) {
+            (SPACE_IO, _) => IoKind::Port,
+            (SPACE_MEMORY, 2) => IoKind::Mmio16,
+            (SPACE_MEMORY, 3) => IoKind::Mmio32,
+            _ => IoKind::Mmio,
+        }
+    }
+}
+
+/// Create the UART for a DBG2 serial subtype at `base`.
+///
+/// `None` for subtypes without a driver here, for address `0`, which
+/// means the port is disabled, and for system I/O off x86_64.
+fn serial_uart(subtype: u16, base: &GenericAddress, f: FnPhysToVirt) -> Option<Uart> {
+    let (compatible, io_kind, reg_shift) = match subtype {
+        SUBTYPE_16550 | SUBTYPE_16550_SUBSET | SUBTYPE_16550_NVIDIA | SUBTYPE_16550_GAS => {
+            let io_kind = base.io_kind();
+            ("ns16550a", io_kind, io_kind.default_reg_shift())
+        }
+        SUBTYPE_PL011 | SUBTYPE_SBSA_32BIT | SUBTYPE_SBSA_GENERIC => {
+            ("arm,pl011", IoKind::Mmio32, 0)
+        }
+        SUBTYPE_BCM2835 => ("brcm,bcm2835-aux-uart", IoKind::Mmio32, 0),
+        _ => return None,
+    };
+
+    if base.address == 0 || !io_kind.is_supported() {
+        return None;
+    }
+
+    Some(Uart::from_match(
+        of_find(compatible)?,
+        base.address,
+        io_kind,
+        reg_shift,
+        f,
+    ))
+}
+
+/// Check the signature, length and checksum of an ACPI table and trim
+/// `bytes` to its length.
+fn table<'a>(bytes: &'a [u8], signature: &[u8; 4]) -> Option<&'a [u8]> {
+    if bytes.get(..4)? != signature {
+        return None;
+    }
+
+    let len = u32_at(bytes, 4)? as usize;
+    let table = bytes.get(..len.max(HEADER_LEN))?;
+
+    let sum = table.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    (sum == 0).then_some(table)
+}
+
+/// # Safety
+///
+/// `ptr` must point to a mapped ACPI table.
+unsafe fn table_from_ptr<'a>(ptr: NonNull<u8>, signature: &[u8; 4]) -> Option<&'a [u8]> {
+    unsafe {
+        let header = core::slice::from_raw_parts(ptr.as_ptr(), HEADER_LEN);
+        let len = u32_at(header, 4)? as usize;
+        let bytes = core::slice::from_raw_parts(ptr.as_ptr(), len.max(HEADER_LEN));
+        table(bytes, signature)
+    }
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Serial Port Console Redirection table.
+#[derive(Clone, Copy)]
+pub struct Spcr<'a> {
+    table: &'a [u8],
+}
+
+impl<'a> Spcr<'a> {
+    /// Revision 1 and 2 tables are 80 bytes, later ones are longer.
+    const MIN_LEN: usize = 80;
+
+    /// `None` if `bytes` doesn't start with a valid SPCR table.
+    pub fn from_bytes(bytes: &'a [u8]) -> Option<Self> {
+        let table = table(bytes, b"SPCR")?;
+        (table.len() >= Self::MIN_LEN).then_some(Self { table })
+    }
+
+    /// # Safety
+    ///
+    /// `ptr` must point to a mapped ACPI table.
+    pub unsafe fn from_ptr(ptr: NonNull<u8>) -> Option<Self> {
+        let table = unsafe { table_from_ptr(ptr, b"SPCR")? };
+        (table.len() >= Self::MIN_LEN).then_some(Self { table })
+    }
+
+    pub fn revision(&self) -> u8 {
+        self.table[8]
+    }
+
+    /// Same values as the DBG2 serial port subtype, e.g. `0x03` for PL011.
+    pub fn interface_type(&self) -> u8 {
+        self.table[36]
+    }
+
+    pub fn base_address(&self) -> GenericAddress {
+        GenericAddress::parse(&self.table[40..]).unwrap()
+    }
+
+    /// Bitmask of the interrupt kinds in use: `1` PC-AT, `2` I/O APIC,
+    /// `4` I/O SAPIC, `8` GIC, `16` RISC-V PLIC/APLIC.
+    pub fn interrupt_type(&self) -> u8 {
+        self.table[52]
+    }
+
+    /// PC-AT compatible IRQ number.
+    pub fn irq(&self) -> u8 {
+        self.table[53]
+    }
+
+    /// Global System Interrupt, e.g. the GIC SPI number plus 32.
+    pub fn gsiv(&self) -> u32 {
+        u32_at(self.table, 54).unwrap()
+    }
+
+    /// Baud rate and line settings the firmware used.
+    ///
+    /// `None` if the baud rate is left "as is".
+    pub fn config(&self) -> Option<Config> {
+        let precise = match self.revision() {
+            4.. => u32_at(self.table, 80).unwrap_or(0),
+            _ => 0,
+        };
+        let baud = match (precise, self.table[58]) {
+            (0, 3) => 9600,
+            (0, 4) => 19200,
+            (0, 6) => 57600,
+            (0, 7) => 115200,
+            (0, _) => return None,
+            (precise, _) => precise,
+        };
+
+        Some(Config {
+            baud,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: self.table[61] & (1 << 1) != 0,
+            ..Default::default()
+        })
+    }
+
+    /// UART reference clock in Hz, `0` if unknown.
+    pub fn clock(&self) -> u32 {
+        match self.revision() {
+            3.. => u32_at(self.table, 76).unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    /// Create the UART, with [`Uart::boot_config`] set from [`Spcr::config`].
+    ///
+    /// `None` if the interface type has no driver here, or redirection is
+    /// disabled.
+    pub fn uart(&self, f: FnPhysToVirt) -> Option<Uart> {
+        let mut uart = serial_uart(self.interface_type() as _, &self.base_address(), f)?;
+        uart.boot_config = self.config();
+        uart.set_input_clock(self.clock());
+        Some(uart)
+    }
+}
+
+/// Debug Port Table 2.
+#[derive(Clone, Copy)]
+pub struct Dbg2<'a> {
+    table: &'a [u8],
+}
+
+impl<'a> Dbg2<'a> {
+    const PORT_TYPE_SERIAL: u16 = 0x8000;
+
+    /// `None` if `bytes` doesn't start with a valid DBG2 table.
+    pub fn from_bytes(bytes: &'a [u8]) -> Option<Self> {
+        table(bytes, b"DBG2").map(|table| Self { table })
+    }
+
+    /// # Safety
+    ///
+    /// `ptr` must point to a mapped ACPI table.
+    pub unsafe fn from_ptr(ptr: NonNull<u8>) -> Option<Self> {
+        unsafe { table_from_ptr(ptr, b"DBG2") }.map(|table| Self { table })
+    }
+
+    /// Every serial debug port, in table order.
+    pub fn serial_ports(&self) -> impl Iterator<Item = Dbg2Port<'a>> + 'a {
+        let table = self.table;
+        let mut offset = u32_at(table, 36).unwrap_or(0) as usize;
+        let count = u32_at(table, 40).unwrap_or(0);
+
+        // Stop at an entry we can't find the end of, but skip one we can't
+        // parse, so the ports after it are still found.
+        (0..count)
+            .map_while(move |_| {
+                let info = table.get(offset..)?;
+                let len = u16_at(info, 1)? as usize;
+                if len == 0 {
+                    return None;
+                }
+                offset += len;
+                info.get(..len)
+            })
+            .filter_map(Dbg2Port::parse)
+            .filter(|port| port.port_type == Self::PORT_TYPE_SERIAL)
+    }
+
+    /// A UART for each serial port that has a driver here.
+    pub fn uarts(&self, f: FnPhysToVirt) -> impl Iterator<Item = Uart> + 'a {
+        self.serial_ports().filter_map(move |port| port.uart(f))
+    }
+}
+
+/// One Debug Device Information entry of [`Dbg2`].
+#[derive(Debug, Clone, Copy)]
+pub struct Dbg2Port<'a> {
+    pub port_type: u16,
+    /// Same values as the SPCR interface type, e.g. `0x03` for PL011.
+    pub port_subtype: u16,
+    /// The first Generic Address Structure, which holds the registers.
+    pub base_address: GenericAddress,
+    /// ACPI namespace path of the device, e.g. `\_SB.COM0`, or `.`.
+    pub namespace: &'a str,
+}
+
+impl<'a> Dbg2Port<'a> {
+    fn parse(info: &'a [u8]) -> Option<Self> {
+        if *info.get(3)? == 0 {
+            return None;
+        }
+
+        let namespace_len = u16_at(info, 4)? as usize;
+        let namespace_offset = u16_at(info, 6)? as usize;
+        let namespace = info.get(namespace_offset..namespace_offset + namespace_len)?;
+        let namespace = core::str::from_utf8(namespace).ok()?;
+
+        Some(Self {
+            port_type: u16_at(info, 12)?,
+            port_subtype: u16_at(info, 14)?,
+            base_address: GenericAddress::parse(info.get(u16_at(info, 18)? as usize..)?)?,
+            namespace: namespace.trim_end_matches('\0'),
+        })
+    }
+
+    /// Create the UART.
+    ///
+    /// `None` if the subtype has no driver here.
+    pub fn uart(&self, f: FnPhysToVirt) -> Option<Uart> {
+        serial_uart(self.port_subtype, &self.base_address, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checksum(table: &mut [u8]) {
+        table[9] = 0;
+        let sum = table.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+        table[9] = sum.wrapping_neg();
+    }
+
+    fn spcr(revision: u8, interface_type: u8, base: GenericAddress) -> [u8; 88] {
+        let mut t = [0u8; 88];
+        t[..4].copy_from_slice(b"SPCR");
+        t[4..8].copy_from_slice(&88u32.to_le_bytes());
+        t[8] = revision;
+        t[36] = interface_type;
+        t[40] = base.space_id;
+        t[41] = base.bit_width;
+        t[42] = base.bit_offset;
+        t[43] = base.access_size;
+        t[44..52].copy_from_slice(&base.address.to_le_bytes());
+        t[52] = 8;
+        t[54..58].copy_from_slice(&33u32.to_le_bytes());
+        t[58] = 7;
+        checksum(&mut t);
+        t
+    }
+
+    const PL011: GenericAddress = GenericAddress {
+        space_id: SPACE_MEMORY,
+        bit_width: 32,
+        bit_offset: 0,
+        access_size: 3,
+        address: 0x900_0000,
+    };
+
+    #[test]
+    fn test_spcr_pl011() {
+        let t = spcr(2, 0x03, PL011);
+        let spcr = Spcr::from_bytes(&t).unwrap();
+        assert_eq!(spcr.base_address(), PL011);
+        assert_eq!(spcr.interrupt_type(), 8);
+        assert_eq!(spcr.gsiv(), 33);
+        assert_eq!(spcr.clock(), 0);
+
+        let uart = spcr.uart(|r| r as _).unwrap();
+        assert_eq!(uart.tx.as_ref().unwrap().mmio(), 0x900_0000);
+        assert_eq!(uart.boot_config(), Some(Config::default()));
+    }
+
+    #[test]
+    fn test_spcr_16550() {
+        let base = GenericAddress {
+            space_id: SPACE_MEMORY,
+            bit_width: 32,
+            bit_offset: 0,
+            access_size: 3,
+            address: 0xfe66_0000,
+        };
+        let mut t = spcr(4, 0x12, base);
+        t[76..80].copy_from_slice(&24_000_000u32.to_le_bytes());
+        t[80..84].copy_from_slice(&1_500_000u32.to_le_bytes());
+        t[61] = 1 << 1;
+        checksum(&mut t);
+
+        let uart = Spcr::from_bytes(&t).unwrap().uart(|r| r as _).unwrap();
+        assert_eq!(uart.data.io_kind(), IoKind::Mmio32);
+        assert_eq!(uart.data.reg_shift(), 2);
+        assert_eq!(uart.input_clock(), 24_000_000);
+        let config = uart.boot_config().unwrap();
+        assert_eq!(config.baud, 1_500_000);
+        assert!(config.flow_control);
+
+        let base = GenericAddress {
+            space_id: SPACE_IO,
+            bit_width: 8,
+            bit_offset: 0,
+            access_size: 1,
+            address: 0x3f8,
+        };
+        let uart = Spcr::from_bytes(&spcr(2, 0x00, base))
+            .unwrap()
+            .uart(|r| r as _);
+        if cfg!(target_arch = "x86_64") {
+            let uart = uart.unwrap();
+            assert_eq!(uart.data.io_kind(), IoKind::Port);
+            assert_eq!(uart.data.base(), 0x3f8);
+        } else {
+            assert!(uart.is_none());
+        }
+    }
+
+    /// A DBG2 table with a PL011, a Qualcomm GENI and a 16550 port.
+    fn dbg2() -> [u8; 44 + 3 * 40] {
+        let mut t = [0u8; 44 + 3 * 40];
+        t[..4].copy_from_slice(b"DBG2");
+        t[4..8].copy_from_slice(&164u32.to_le_bytes());
+        t[36..40].copy_from_slice(&44u32.to_le_bytes());
+        t[40..44].copy_from_slice(&3u32.to_le_bytes());
+
+        let ports = [
+            (0x03u16, 0x900_0000u64),
+            (0x11, 0x4a9_0000),
+            (0x00, 0x1000_0000),
+        ];
+        for (i, (subtype, address)) in ports.into_iter().enumerate() {
+            let info = &mut t[44 + i * 40..][..40];
+            info[1..3].copy_from_slice(&40u16.to_le_bytes());
+            info[3] = 1;
+            // Namespace "." at 34.
+            info[4..6].copy_from_slice(&2u16.to_le_bytes());
+            info[6..8].copy_from_slice(&34u16.to_le_bytes());
+            info[12..14].copy_from_slice(&0x8000u16.to_le_bytes());
+            info[14..16].copy_from_slice(&subtype.to_le_bytes());
+            // Base address at 22, address size at 34.
+            info[18..20].copy_from_slice(&22u16.to_le_bytes());
+            info[20..22].copy_from_slice(&34u16.to_le_bytes());
+            info[22] = SPACE_MEMORY;
+            info[23] = 32;
+            info[25] = 3;
+            info[26..34].copy_from_slice(&address.to_le_bytes());
+            info[34] = b'.';
+        }
+        checksum(&mut t);
+        t
+    }
+
+    #[test]
+    fn test_dbg2() {
+        let t = dbg2();
+        let dbg2 = Dbg2::from_bytes(&t).unwrap();
+
+        let ports: Vec<_> = dbg2.serial_ports().collect();
+        assert_eq!(ports.len(), 3);
+        assert_eq!(ports[1].port_subtype, 0x11);
+        assert_eq!(ports[2].base_address.address, 0x1000_0000);
+        assert_eq!(ports[2].namespace, ".");
+
+        let uarts: Vec<_> = dbg2.uarts(|r| r as _).collect();
+        assert_eq!(uarts.len(), 2);
+        assert_eq!(uarts[0].tx.as_ref().unwrap().mmio(), 0x900_0000);
+        assert_eq!(uarts[1].data.io_kind(), IoKind::Mmio32);
+        assert_eq!(uarts[1].data.reg_shift(), 2);
+
+        assert!(Spcr::from_bytes(&t).is_none());
+    }
+
+    #[test]
+    fn test_dbg2_bad_entry() {
+        let mut t = dbg2();
+        t[44 + 3] = 0; // PL011: no Generic Address Structure
+        t[44 + 40 + 34] = 0xff; // GENI: namespace not UTF-8
+        checksum(&mut t);
+
+        let dbg2 = Dbg2::from_bytes(&t).unwrap();
+        let ports: Vec<_> = dbg2.serial_ports().collect();
+        assert_eq!(ports.len(), 1);
+        assert_eq!(ports[0].base_address.address, 0x1000_0000);
+    }
+
+    #[test]
+    fn test_spcr_invalid() {
+        let mut t = spcr(2, 0x03, PL011);
+        t[20] ^= 1;
+        assert!(Spcr::from_bytes(&t).is_none());
+
+        let mut t = spcr(2, 0x03, PL011);
+        t[..4].copy_from_slice(b"DBG2");
+        checksum(&mut t);
+        assert!(Spcr::from_bytes(&t).is_none());
+
+        // Qualcomm GENI has no driver here.
+        let t = spcr(2, 0x11, PL011);
+        assert!(Spcr::from_bytes(&t).unwrap().uart(|r| r as _).is_none());
+
+        // Address 0 means redirection is disabled.
+        let t = spcr(
+            2,
+            0x03,
+            GenericAddress {
+                address: 0,
+                ..PL011
+            },
+        );
+        assert!(Spcr::from_bytes(&t).unwrap().uart(|r| r as _).is_none());
+    }
+}
@@ -1,7 +1,58 @@
-use crate::{Console, ErrorKind, IrqEvent, UartData};
+use crate::{
+    Config, ConfigError, Console, DataBits, ErrorKind, IrqEvent, IrqMask, Parity, StopBits,
+    UartData,
+};
+
+/// `AUX_ENABLES` is in the AUX block, `0x3c` bytes before `AUX_MU_IO`,
+/// which is the base in the device tree.
+const AUX_ENABLES_OFFSET: usize = 0x40 - 0x04;
+const AUX_ENABLES_MINI_UART: u32 = 1 << 0;
+
+// Offsets from `AUX_MU_IO`, in 32-bit registers.
+const IER: usize = 1;
+const IIR: usize = 0x08 / 4;
+const LCR: usize = 0x0C / 4;
+const CNTL: usize = 0x20 / 4;
+const STAT: usize = 0x24 / 4;
+const BAUD: usize = 0x28 / 4;
+
+const CNTL_RX_ENABLE: u32 = 1 << 0;
+const CNTL_TX_ENABLE: u32 = 1 << 1;
+/// De-assert RTS when the RX FIFO is almost full.
+const CNTL_RTS_FLOW: u32 = 1 << 2;
+/// Stop transmitting while CTS is de-asserted.
+const CNTL_CTS_FLOW: u32 = 1 << 3;
+
+/// TX FIFO empty and the transmitter idle.
+const STAT_TX_DONE: u32 = 1 << 9;
+
+/// Receive interrupt. The datasheet swaps this and `IER_TX`.
+const IER_RX: u32 = 1 << 0;
+const IER_TX: u32 = 1 << 1;
+/// Documented as don't care, but BCM2835 ARM Peripherals erratum: RX
+/// interrupts are only delivered with both set.
+const IER_RX_ERRATUM: u32 = 0b11 << 2;
+
+/// No interrupt pending, active high.
+const IIR_NO_INT: u32 = 1 << 0;
+/// Interrupt ID on read. Writing these bits clears the FIFOs instead.
+const IIR_ID: u32 = 0b11 << 1;
+const IIR_TX: u32 = 0b01 << 1;
+const IIR_RX: u32 = 0b10 << 1;
+/// Written: clear both FIFOs.
+const IIR_CLEAR_FIFOS: u32 = 0b11 << 1;
+
+/// 8-bit mode. The datasheet documents only bit 0, but bit 1 is needed too.
+const LCR_8BIT: u32 = 0b11;
 
 pub struct AuxMini {}
 
+impl AuxMini {
+    fn aux_enables(uart: UartData) -> *mut u32 {
+        (uart.base - AUX_ENABLES_OFFSET) as _
+    }
+}
+
 impl Console for AuxMini {
     fn put(uart: UartData, byte: u8) -> Result<(), ErrorKind> {
         unsafe {
@@ -19,14 +70,107 @@ impl Console for AuxMini {
         }
     }
 
-    fn set_irq_enable(_uart: UartData, _enable: bool) {}
+    fn set_irq_enable(uart: UartData, enable: bool) {
+        Self::set_irq_mask(
+            uart,
+            IrqMask {
+                rx: enable,
+                tx: enable,
+                ..Default::default()
+            },
+        );
+    }
+
+    fn get_irq_enable(uart: UartData) -> bool {
+        unsafe { uart.reg::<u32>(IER).read_volatile() & (IER_RX | IER_TX) != 0 }
+    }
 
-    fn get_irq_enable(_uart: UartData) -> bool {
-        todo!()
+    /// There is no separate RX timeout, line status or modem status
+    /// interrupt.
+    fn set_irq_mask(uart: UartData, mask: IrqMask) {
+        let mut ier = 0;
+        if mask.rx || mask.rx_timeout {
+            ier |= IER_RX | IER_RX_ERRATUM;
+        }
+        if mask.tx {
+            ier |= IER_TX;
+        }
+
+        unsafe { uart.reg::<u32>(IER).write_volatile(ier) }
+    }
+
+    fn get_irq_mask(uart: UartData) -> IrqMask {
+        let ier = unsafe { uart.reg::<u32>(IER).read_volatile() };
+        IrqMask {
+            rx: ier & IER_RX != 0,
+            tx: ier & IER_TX != 0,
+            rx_timeout: ier & IER_RX != 0,
+            line_status: false,
+            modem_status: false,
+        }
     }
 
+    /// RX is cleared by reading the data and TX by writing it, so there is
+    /// nothing to do. IIR is not written, as that would flush the FIFOs.
     fn clean_irq_event(_uart: UartData, _event: IrqEvent) {}
 
+    /// Also enables the mini UART in `AUX_ENABLES`, so no firmware setup is
+    /// needed. The input clock is the VPU core clock, which the device tree
+    /// doesn't give as a fixed rate: set it with [`crate::Uart::set_input_clock`].
+    fn set_config(uart: UartData, config: &Config) -> Result<(), ConfigError> {
+        if config.parity != Parity::None || config.stop_bits != StopBits::One {
+            return Err(ConfigError::Unsupported);
+        }
+
+        let lcr = match config.data_bits {
+            DataBits::Seven => 0,
+            DataBits::Eight => LCR_8BIT,
+            _ => return Err(ConfigError::Unsupported),
+        };
+
+        if uart.clock == 0 {
+            return Err(ConfigError::UnknownClock);
+        }
+
+        // baud = clock / (8 * (reg + 1))
+        let baud = config.baud as u64 * 8;
+        let div = (uart.clock as u64 + baud / 2)
+            .checked_div(baud)
+            .unwrap_or(0);
+        if div == 0 || div > 0x10000 {
+            return Err(ConfigError::InvalidBaud);
+        }
+
+        let mut new_cntl = CNTL_RX_ENABLE | CNTL_TX_ENABLE;
+        if config.flow_control {
+            new_cntl |= CNTL_RTS_FLOW | CNTL_CTS_FLOW;
+        }
+
+        unsafe {
+            // Shared with the two SPI masters.
+            let enables = Self::aux_enables(uart);
+            let old_enables = enables.read_volatile();
+            enables.write_volatile(old_enables | AUX_ENABLES_MINI_UART);
+
+            // Let queued bytes go out, unless the transmitter is off and
+            // they never will: clearing the FIFOs drops them then.
+            let cntl = uart.reg::<u32>(CNTL);
+            if old_enables & AUX_ENABLES_MINI_UART != 0
+                && cntl.read_volatile() & CNTL_TX_ENABLE != 0
+            {
+                while uart.reg::<u32>(STAT).read_volatile() & STAT_TX_DONE == 0 {}
+            }
+
+            cntl.write_volatile(0);
+            uart.reg::<u32>(LCR).write_volatile(lcr);
+            uart.reg::<u32>(BAUD).write_volatile(div as u32 - 1);
+            uart.reg::<u32>(IIR).write_volatile(IIR_CLEAR_FIFOS);
+            cntl.write_volatile(new_cntl);
+        }
+
+        Ok(())
+    }
+
     fn can_put(uart: UartData) -> bool {
         const TXFF: u32 = 1 << 5;
         let state = uart.reg_u8(0x24) as *const u32;
@@ -39,7 +183,16 @@ impl Console for AuxMini {
         unsafe { state.read_volatile() & RX_READY != 0 }
     }
 
-    fn get_irq_event(_uart: UartData) -> IrqEvent {
-        todo!()
+    fn get_irq_event(uart: UartData) -> IrqEvent {
+        let iir = unsafe { uart.reg::<u32>(IIR).read_volatile() };
+        if iir & IIR_NO_INT != 0 {
+            return IrqEvent::empty();
+        }
+
+        match iir & IIR_ID {
+            IIR_RX => IrqEvent::RX,
+            IIR_TX => IrqEvent::TX,
+            _ => IrqEvent::empty(),
+        }
     }
 }
@@ -0,0 +1,159 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataBits {
+    Five,
+    Six,
+    Seven,
+    Eight,
+}
+
+impl DataBits {
+    pub fn bits(&self) -> u8 {
+        match self {
+            DataBits::Five => 5,
+            DataBits::Six => 6,
+            DataBits::Seven => 7,
+            DataBits::Eight => 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Parity {
+    None,
+    Odd,
+    Even,
+    /// Parity bit always 1.
+    Mark,
+    /// Parity bit always 0.
+    Space,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopBits {
+    One,
+    Two,
+}
+
+/// Line configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub baud: u32,
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    /// RTS/CTS hardware flow control.
+    pub flow_control: bool,
+}
+
+impl Default for Config {
+    /// 115200 8N1
+    fn default() -> Self {
+        Self {
+            baud: 115200,
+            data_bits: DataBits::Eight,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: false,
+        }
+    }
+}
+
+impl Config {
+    /// Parse console options `<baud><parity><bits><flow>`, e.g. `115200n8`
+    /// or `9600e7r`, like Linux `uart_parse_options`.
+    ///
+    /// Everything after the baud rate is optional and defaults to 8N1
+    /// without flow control. Returns `None` if there is no baud rate.
+    pub fn parse_options(options: &str) -> Option<Self> {
+        let digits = options
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(options.len());
+        let (baud, rest) = options.split_at(digits);
+
+        let mut config = Config {
+            baud: baud.parse().ok()?,
+            ..Default::default()
+        };
+
+        let mut rest = rest.bytes();
+
+        if let Some(parity) = rest.next() {
+            config.parity = match parity {
+                b'o' | b'O' => Parity::Odd,
+                b'e' | b'E' => Parity::Even,
+                b'm' | b'M' => Parity::Mark,
+                b's' | b'S' => Parity::Space,
+                _ => Parity::None,
+            };
+        }
+
+        if let Some(bits) = rest.next() {
+            config.data_bits = match bits {
+                b'5' => DataBits::Five,
+                b'6' => DataBits::Six,
+                b'7' => DataBits::Seven,
+                _ => DataBits::Eight,
+            };
+        }
+
+        config.flow_control = rest.next() == Some(b'r');
+
+        Some(config)
+    }
+}
+
+/// FIFO interrupt trigger level, as a fraction of the FIFO depth.
+///
+/// Drivers round it to the nearest level the hardware has.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FifoLevel {
+    OneEighth,
+    OneQuarter,
+    #[default]
+    Half,
+    ThreeQuarters,
+    SevenEighths,
+}
+
+/// FIFO interrupt trigger levels.
+///
+/// The RX interrupt fires once the RX FIFO fills to `rx_level`, and the TX
+/// interrupt once the TX FIFO drains to `tx_level`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FifoConfig {
+    pub rx_level: FifoLevel,
+    pub tx_level: FifoLevel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// The input clock is unknown, see [`crate::Uart::set_input_clock`].
+    UnknownClock,
+    /// The baud rate is out of the divisor range for the input clock.
+    InvalidBaud,
+    /// The driver or hardware can't do this setting.
+    Unsupported,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!(Config::parse_options("115200n8"), Some(Config::default()));
+        assert_eq!(
+            Config::parse_options("9600e7r"),
+            Some(Config {
+                baud: 9600,
+                data_bits: DataBits::Seven,
+                parity: Parity::Even,
+                stop_bits: StopBits::One,
+                flow_control: true,
+            })
+        );
+        assert_eq!(Config::parse_options("1500000").unwrap().baud, 1_500_000);
+        assert_eq!(Config::parse_options("n8"), None);
+        assert_eq!(Config::parse_options(""), None);
+    }
+}
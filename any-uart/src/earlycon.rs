@@ -0,0 +1,290 @@
+use crate::{Config, FnPhysToVirt, IoKind, OfMatch, Uart, of::of_find};
+
+/// Earlycon names and the compatible whose driver handles them.
+const EARLYCON_NAMES: &[(&str, &str)] = &[
+    ("pl011", "arm,pl011"),
+    ("uart", "ns16550a"),
+    ("uart8250", "ns16550a"),
+    ("ns16550", "ns16550"),
+    ("ns16550a", "ns16550a"),
+    ("bcm2835aux", "brcm,bcm2835-aux-uart"),
+];
+
+/// A parsed `earlycon` kernel argument.
+#[derive(Clone, Copy)]
+pub enum Earlycon<'a> {
+    /// Bare `earlycon`: the console is `/chosen/stdout-path`.
+    StdoutPath,
+    /// `earlycon=sbi`: the RISC-V SBI console, not a UART.
+    Sbi,
+    Uart(EarlyconUart<'a>),
+}
+
+/// `earlycon=<name>[,<io kind>][,<addr>][,<options>]`
+#[derive(Clone, Copy)]
+pub struct EarlyconUart<'a> {
+    /// The `<name>` part, e.g. `uart8250`.
+    pub name: &'a str,
+    pub driver: &'static OfMatch,
+    pub io_kind: IoKind,
+    /// Register shift implied by `io_kind`, as Linux does.
+    pub reg_shift: u8,
+    /// Physical address, or the port number for [`IoKind::Port`].
+    ///
+    /// `None` for `earlycon=<name>`, which names the driver of `stdout-path`.
+    pub addr: Option<u64>,
+    /// The `<options>` part, e.g. `115200n8`.
+    pub config: Option<Config>,
+    /// Reference clock in Hz when implied by the name, else `0`.
+    pub clock: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EarlyconError<'a> {
+    /// No driver for this earlycon name.
+    UnsupportedName(&'a str),
+    /// The address is not a number.
+    InvalidAddress(&'a str),
+    /// This target can't do this kind of access, see [`IoKind::is_supported`].
+    UnsupportedIoKind(IoKind),
+}
+
+impl<'a> Earlycon<'a> {
+    /// Find and parse the first `earlycon` argument of `bootargs`.
+    pub fn from_bootargs(bootargs: &'a str) -> Option<Result<Self, EarlyconError<'a>>> {
+        bootargs
+            .split_ascii_whitespace()
+            .find(|arg| *arg == "earlycon" || arg.starts_with("earlycon="))
+            .map(Self::parse)
+    }
+
+    /// Parse one `earlycon` or `earlycon=...` argument.
+    ///
+    /// The address may be hex with `0x`, or decimal.
+    pub fn parse(arg: &'a str) -> Result<Self, EarlyconError<'a>> {
+        let value = arg.strip_prefix("earlycon").unwrap_or(arg);
+        let value = value.strip_prefix('=').unwrap_or(value);
+
+        if value.is_empty() {
+            return Ok(Earlycon::StdoutPath);
+        }
+
+        let (name, mut rest) = match value.split_once(',') {
+            Some((name, rest)) => (name, Some(rest)),
+            None => (value, None),
+        };
+
+        if name == "sbi" {
+            return Ok(Earlycon::Sbi);
+        }
+
+        let driver = EARLYCON_NAMES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .and_then(|(_, compatible)| of_find(compatible))
+            .ok_or(EarlyconError::UnsupportedName(name))?;
+
+        let mut io_kind = IoKind::Mmio;
+        if let Some(kind) = rest
+            .and_then(|r| r.split(',').next())
+            .and_then(IoKind::parse)
+        {
+            io_kind = kind;
+            rest = rest.and_then(|r| r.split_once(',')).map(|(_, r)| r);
+        }
+        if !io_kind.is_supported() {
+            return Err(EarlyconError::UnsupportedIoKind(io_kind));
+        }
+
+        let mut addr = None;
+        let mut config = None;
+        if let Some(rest) = rest {
+            let (addr_str, options) = match rest.split_once(',') {
+                Some((a, o)) => (a, Some(o)),
+                None => (rest, None),
+            };
+            addr = Some(parse_addr(addr_str).ok_or(EarlyconError::InvalidAddress(addr_str))?);
+            config = options.and_then(Config::parse_options);
+        }
+
+        Ok(Earlycon::Uart(EarlyconUart {
+            name,
+            driver,
+            io_kind,
+            reg_shift: io_kind.default_reg_shift(),
+            addr,
+            config,
+            clock: 0,
+        }))
+    }
+}
+
+impl<'a> EarlyconUart<'a> {
+    /// Parse the value of a `console=` argument that names a UART by address,
+    /// e.g. `uart8250,io,0x3f8,115200n8`, or on x86_64 a legacy `ttyS<n>`.
+    ///
+    /// `None` for consoles like `tty0` or `ttyAMA0` whose address is unknown.
+    pub fn from_console(value: &'a str) -> Option<Self> {
+        #[cfg(target_arch = "x86_64")]
+        if let Some(uart) = Self::legacy_port(value) {
+            return Some(uart);
+        }
+
+        match Earlycon::parse(value) {
+            Ok(Earlycon::Uart(uart)) if uart.addr.is_some() => Some(uart),
+            _ => None,
+        }
+    }
+
+    /// `ttyS0` to `ttyS3` are the PC COM ports.
+    #[cfg(target_arch = "x86_64")]
+    fn legacy_port(value: &'a str) -> Option<Self> {
+        const PORTS: [u64; 4] = [0x3f8, 0x2f8, 0x3e8, 0x2e8];
+
+        let (name, options) = match value.split_once(',') {
+            Some((name, options)) => (name, Some(options)),
+            None => (value, None),
+        };
+        let index: usize = name.strip_prefix("ttyS")?.parse().ok()?;
+
+        Some(Self {
+            name,
+            driver: of_find("ns16550a")?,
+            io_kind: IoKind::Port,
+            reg_shift: 0,
+            addr: Some(*PORTS.get(index)?),
+            config: options.and_then(Config::parse_options),
+            clock: 1_843_200,
+        })
+    }
+
+    /// Create the UART straight from the command line, without a device tree.
+    ///
+    /// `None` if the argument has no address, or if [`EarlyconUart::io_kind`]
+    /// is not supported on this target.
+    pub fn uart(&self, f: FnPhysToVirt) -> Option<Uart> {
+        if !self.io_kind.is_supported() {
+            return None;
+        }
+        let mut uart = Uart::from_match(self.driver, self.addr?, self.io_kind, self.reg_shift, f);
+        uart.boot_config = self.config;
+        uart.set_input_clock(self.clock);
+        Some(uart)
+    }
+}
+
+fn parse_addr(s: &str) -> Option<u64> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uart(arg: &str) -> EarlyconUart<'_> {
+        match Earlycon::parse(arg) {
+            Ok(Earlycon::Uart(uart)) => uart,
+            _ => panic!("{arg}"),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let e = uart("earlycon=pl011,0x9000000");
+        assert_eq!(e.driver.driver, "pl011");
+        assert_eq!(e.io_kind, IoKind::Mmio);
+        assert_eq!(e.addr, Some(0x900_0000));
+        assert_eq!(e.config, None);
+
+        let e = uart("earlycon=pl011,mmio32,0x9000000,115200");
+        assert_eq!(e.io_kind, IoKind::Mmio32);
+        assert_eq!(e.reg_shift, 2);
+        assert_eq!(e.addr, Some(0x900_0000));
+        assert_eq!(e.config.unwrap().baud, 115200);
+
+        let e = uart("earlycon=uart8250,io,0x3f8,115200n8");
+        assert_eq!(e.driver.driver, "ns16550");
+        assert_eq!(e.io_kind, IoKind::Port);
+        assert_eq!(e.addr, Some(0x3f8));
+        assert_eq!(e.config, Some(Config::default()));
+
+        let e = uart("earlycon=uart,mmio32,0xfe660000");
+        assert_eq!(e.io_kind, IoKind::Mmio32);
+        assert_eq!(e.addr, Some(0xfe66_0000));
+
+        let e = uart("earlycon=bcm2835aux,0x3f215040");
+        assert_eq!(e.driver.driver, "aux_mini");
+
+        let e = uart("earlycon=pl011");
+        assert_eq!(e.addr, None);
+
+        assert!(matches!(
+            Earlycon::parse("earlycon"),
+            Ok(Earlycon::StdoutPath)
+        ));
+        assert!(matches!(
+            Earlycon::parse("earlycon="),
+            Ok(Earlycon::StdoutPath)
+        ));
+        assert!(matches!(Earlycon::parse("earlycon=sbi"), Ok(Earlycon::Sbi)));
+        assert_eq!(
+            Earlycon::parse("earlycon=qcom_geni,0x4a90000").err(),
+            Some(EarlyconError::UnsupportedName("qcom_geni"))
+        );
+        assert_eq!(
+            Earlycon::parse("earlycon=uart,mmio32,fe660000").err(),
+            Some(EarlyconError::InvalidAddress("fe660000"))
+        );
+    }
+
+    #[test]
+    fn test_port_io() {
+        let e = Earlycon::parse("earlycon=uart8250,io,0x3f8");
+        if cfg!(target_arch = "x86_64") {
+            assert!(matches!(e, Ok(Earlycon::Uart(_))));
+        } else {
+            assert_eq!(
+                e.err(),
+                Some(EarlyconError::UnsupportedIoKind(IoKind::Port))
+            );
+        }
+
+        let mut e = uart("earlycon=uart8250,mmio32,0x3f8");
+        assert!(e.uart(|addr| addr as _).is_some());
+        e.io_kind = IoKind::Port;
+        assert_eq!(
+            e.uart(|addr| addr as _).is_some(),
+            cfg!(target_arch = "x86_64")
+        );
+    }
+
+    #[test]
+    fn test_from_bootargs() {
+        let args = "console=ttyS0 earlycon=uart8250,mmio32,0xfe660000 root=/dev/mmcblk0p2";
+        let Some(Ok(Earlycon::Uart(e))) = Earlycon::from_bootargs(args) else {
+            panic!()
+        };
+        assert_eq!(e.addr, Some(0xfe66_0000));
+
+        assert!(Earlycon::from_bootargs("console=ttyS0 earlycon_foo=1").is_none());
+    }
+
+    #[test]
+    fn test_from_console() {
+        let c = EarlyconUart::from_console("uart8250,mmio32,0xfe660000,1500000n8").unwrap();
+        assert_eq!(c.addr, Some(0xfe66_0000));
+        assert_eq!(c.config.unwrap().baud, 1_500_000);
+
+        let c = EarlyconUart::from_console("ttyS1,9600").unwrap();
+        assert_eq!(c.io_kind, IoKind::Port);
+        assert_eq!(c.addr, Some(0x2f8));
+        assert_eq!(c.clock, 1_843_200);
+
+        assert!(EarlyconUart::from_console("tty0").is_none());
+        assert!(EarlyconUart::from_console("ttyAMA0,115200").is_none());
+        assert!(EarlyconUart::from_console("pl011").is_none());
+    }
+}
@@ -15,72 +15,255 @@ pub use core::fmt::Write;
 pub use embedded_hal_nb::nb::block;
 pub use embedded_hal_nb::serial::ErrorKind;
 
+pub use acpi::{Dbg2, Dbg2Port, GenericAddress, Spcr};
 use aux_mini::AuxMini;
+pub use config::{Config, ConfigError, DataBits, FifoConfig, FifoLevel, Parity, StopBits};
+pub use earlycon::{Earlycon, EarlyconError, EarlyconUart};
 pub use fdt_parser::Node;
-use fdt_parser::{Chosen, Fdt};
+use fdt_parser::{Fdt, Status};
 use ns16550::Ns16550;
+pub use of::{IrqSpec, OF_MATCH_TABLE, OfMatch, of_match, of_match_with, serial_alias};
+use of::{
+    fdt_find_node_by_addr, fdt_find_path, fdt_input_clock, fdt_io_config, fdt_irq, fdt_reg_address,
+};
 use pl011::Pl011;
 
+mod acpi;
 mod aux_mini;
+mod config;
+mod earlycon;
 mod ns16550;
+mod of;
 mod pl011;
 
 pub type Error = embedded_hal_nb::nb::Error<ErrorKind>;
 pub type FnPhysToVirt = fn(usize) -> *mut u8;
 
+/// Why a UART could not be created from the device tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProbeError<'a> {
+    /// The device tree blob is invalid.
+    InvalidFdt,
+    /// The device tree has no `/chosen` node.
+    NoChosen,
+    /// Neither `earlycon=<name>,<addr>` in `bootargs` nor `stdout-path`.
+    NoStdoutPath,
+    /// The `earlycon` argument names no UART we can drive, and there is no
+    /// `stdout-path` to fall back to.
+    BadEarlycon(EarlyconError<'a>),
+    /// `/aliases` has no such entry.
+    NoAlias(&'a str),
+    /// No node at this path.
+    NoNode(&'a str),
+    /// The node has `status = "disabled"`.
+    Disabled,
+    /// The node has no `reg`.
+    NoReg,
+    /// `reg` is outside the `ranges` of a bus above the node.
+    Untranslatable,
+    /// None of the node's compatibles has a driver; holds the first one.
+    UnsupportedCompatible(&'a str),
+}
+
 pub struct Uart {
     data: UartData,
     pub tx: Option<Sender>,
     pub rx: Option<Receiver>,
     op: UartOp,
+    boot_config: Option<Config>,
+    irq: Option<IrqSpec>,
 }
 
 impl Uart {
-    fn _new<C: Console>(data: UartData) -> Self {
-        let op = C::to_op();
+    const fn _new<C: Console>(data: UartData) -> Self {
+        Self::from_op(data, C::OP)
+    }
 
+    const fn from_op(data: UartData, op: UartOp) -> Self {
         Self {
             data,
             tx: Some(Sender { uart: data, op }),
             rx: Some(Receiver { uart: data, op }),
             op,
+            boot_config: None,
+            irq: None,
         }
     }
 
-    pub fn new_port_8250(base: usize) -> Self {
-        let data = UartData::new(base as _, IoKind::Port, |p| p as _);
+    pub const fn new_port_8250(base: usize) -> Self {
+        Self::new::<Ns16550>(base, IoKind::Port, 0)
+    }
+
+    /// Create a UART driven by `C`, without a device tree.
+    ///
+    /// `base` must already be mapped: it is used as is, no phys-to-virt
+    /// translation is applied.
+    pub const fn new<C: Console>(base: usize, io_kind: IoKind, reg_shift: u8) -> Self {
+        Self::_new::<C>(UartData::from_virt(base, io_kind, reg_shift))
+    }
+
+    /// PL011 at the mapped address `base`.
+    pub const fn new_pl011(base: usize) -> Self {
+        Self::new::<Pl011>(base, IoKind::Mmio32, 0)
+    }
+
+    /// 8250/16550 at the mapped address `base`.
+    pub const fn new_ns16550_mmio(base: usize, io_kind: IoKind, reg_shift: u8) -> Self {
+        Self::new::<Ns16550>(base, io_kind, reg_shift)
+    }
+
+    /// Synopsys DesignWare APB UART at the mapped address `base`.
+    pub const fn new_dw_apb(base: usize) -> Self {
+        let mut data = UartData::from_virt(base, IoKind::Mmio32, 2);
+        data.quirks = Quirks::DW_APB;
         Self::_new::<Ns16550>(data)
     }
 
+    /// BCM2835 mini UART, `base` is the mapped address of `AUX_MU_IO`.
+    pub const fn new_aux_mini(base: usize) -> Self {
+        Self::new::<AuxMini>(base, IoKind::Mmio32, 0)
+    }
+
     pub fn mmio_base_add(&mut self, offset: usize) {
         self.data.base += offset;
     }
 
-    pub fn new_by_fdt_node(node: &Node<'_>, f: FnPhysToVirt) -> Option<Self> {
-        let reg = node.reg()?.next()?;
+    pub fn new_by_fdt_node<'a>(node: &Node<'a>, f: FnPhysToVirt) -> Result<Self, ProbeError<'a>> {
+        Self::new_by_fdt_node_with_drivers(node, f, &[])
+    }
+
+    /// Like [`Uart::new_by_fdt_node`], but `drivers` are tried before
+    /// [`OF_MATCH_TABLE`].
+    pub fn new_by_fdt_node_with_drivers<'a>(
+        node: &Node<'a>,
+        f: FnPhysToVirt,
+        drivers: &[OfMatch],
+    ) -> Result<Self, ProbeError<'a>> {
+        if node.status() == Some(Status::Disabled) {
+            return Err(ProbeError::Disabled);
+        }
+        Self::new_by_fdt_node_any_status(node, f, drivers)
+    }
 
-        let io_kind = IoKind::Mmio32;
+    /// Like [`Uart::new_by_fdt_node_with_drivers`], but also accepts nodes
+    /// with `status = "disabled"`, e.g. a console the firmware set up anyway.
+    pub fn new_by_fdt_node_any_status<'a>(
+        node: &Node<'a>,
+        f: FnPhysToVirt,
+        drivers: &[OfMatch],
+    ) -> Result<Self, ProbeError<'a>> {
+        let m = of_match_with(node, drivers).ok_or_else(|| {
+            ProbeError::UnsupportedCompatible(node.compatibles().next().unwrap_or_default())
+        })?;
+        let addr = fdt_reg_address(node)?;
+
+        let (io_kind, reg_shift) = fdt_io_config(node, m.io_kind, m.reg_shift);
+        let mut uart = Self::from_match(m, addr, io_kind, reg_shift, f);
+        uart.set_input_clock(fdt_input_clock(node).unwrap_or(0));
+        uart.irq = fdt_irq(node);
+
+        Ok(uart)
+    }
 
-        // TODO: support io kind detect
+    /// Create the UART of the node that `/aliases` names `alias`, e.g. `serial2`.
+    pub fn new_by_alias<'a>(
+        fdt: &'a [u8],
+        alias: &'a str,
+        f: FnPhysToVirt,
+    ) -> Result<Self, ProbeError<'a>> {
+        let fdt = Fdt::from_bytes(fdt).map_err(|_| ProbeError::InvalidFdt)?;
+        let path = fdt.find_aliase(alias).ok_or(ProbeError::NoAlias(alias))?;
+        let node = fdt_find_path(&fdt, path)?;
+
+        Self::new_by_fdt_node(&node, f)
+    }
 
-        let uart = UartData::new(reg.address, io_kind, f);
+    pub(crate) fn from_match(
+        m: &OfMatch,
+        addr: u64,
+        io_kind: IoKind,
+        reg_shift: u8,
+        f: FnPhysToVirt,
+    ) -> Self {
+        let mut data = UartData::new(addr, io_kind, reg_shift, f);
+        data.quirks = m.quirks;
+        Self::from_op(data, (m.op)())
+    }
 
-        for c in node.compatibles() {
-            macro_rules! of_uart {
-                ($name:ty, $compatible:expr) => {
-                    for want in $compatible {
-                        if c.contains(want) {
-                            return Some(Uart::_new::<$name>(uart));
-                        }
-                    }
-                };
-            }
+    /// Frequency of the UART reference clock in Hz, `0` if unknown.
+    ///
+    /// Read from the device tree node when the UART is created from one.
+    pub fn input_clock(&self) -> u32 {
+        self.data.clock
+    }
+
+    /// Set the reference clock used to compute baud rate divisors.
+    pub fn set_input_clock(&mut self, hz: u32) {
+        self.data.clock = hz;
+        if let Some(tx) = &mut self.tx {
+            tx.uart.clock = hz;
+        }
+        if let Some(rx) = &mut self.rx {
+            rx.uart.clock = hz;
+        }
+    }
+
+    /// Enable the FIFOs, if any, with the default trigger levels, and return
+    /// how many bytes [`Sender::write_fifo`] writes at once.
+    ///
+    /// Until then it writes one byte at a time.
+    pub fn enable_fifo(&mut self) -> usize {
+        let depth = (self.op.enable_fifo)(self.data, &FifoConfig::default());
+        self.set_fifo_depth(depth);
+        depth
+    }
+
+    /// Bring the UART up from reset: program the line and the FIFOs, then
+    /// enable it. For when nothing, e.g. firmware, has set it up before.
+    ///
+    /// Returns the same as [`Uart::enable_fifo`].
+    pub fn init(&mut self, config: Config, fifo: FifoConfig) -> Result<usize, ConfigError> {
+        let depth = (self.op.init)(self.data, &config, &fifo)?;
+        self.set_fifo_depth(depth);
+        Ok(depth)
+    }
+
+    fn set_fifo_depth(&mut self, depth: usize) {
+        self.data.fifo_depth = depth;
+        if let Some(tx) = &mut self.tx {
+            tx.uart.fifo_depth = depth;
+        }
+        if let Some(rx) = &mut self.rx {
+            rx.uart.fifo_depth = depth;
+        }
+    }
+
+    /// Program baud rate, data bits, parity and stop bits.
+    ///
+    /// The baud rate divisor is computed from [`Uart::input_clock`].
+    pub fn set_config(&mut self, config: Config) -> Result<(), ConfigError> {
+        (self.op.set_config)(self.data, &config)
+    }
 
-            of_uart!(AuxMini, ["brcm,bcm2835-aux-uart"]);
-            of_uart!(Pl011, ["arm,pl011", "arm,primecell"]);
-            of_uart!(Ns16550, ["snps,dw-apb-uart"]);
+    /// Line configuration requested by the boot environment, e.g. the
+    /// `115200n8` of `stdout-path = "serial0:115200n8"`.
+    ///
+    /// It is not applied to the hardware, see [`Uart::apply_boot_config`].
+    pub fn boot_config(&self) -> Option<Config> {
+        self.boot_config
+    }
+
+    /// Program [`Uart::boot_config`], if any.
+    pub fn apply_boot_config(&mut self) -> Result<(), ConfigError> {
+        match self.boot_config {
+            Some(config) => self.set_config(config),
+            None => Ok(()),
         }
-        None
+    }
+
+    /// First interrupt of the device tree node, to hook up at its controller.
+    pub fn irq(&self) -> Option<IrqSpec> {
+        self.irq
     }
 
     pub fn set_irq_enable(&mut self, enable: bool) {
@@ -91,6 +274,16 @@ impl Uart {
         (self.op.get_irq_enable)(self.data)
     }
 
+    /// Enable exactly the interrupts in `mask`.
+    pub fn set_irq_mask(&mut self, mask: IrqMask) {
+        (self.op.set_irq_mask)(self.data, mask);
+    }
+
+    /// The interrupts enabled in hardware.
+    pub fn get_irq_mask(&mut self) -> IrqMask {
+        (self.op.get_irq_mask)(self.data)
+    }
+
     pub fn clean_irq_event(&mut self, event: IrqEvent) {
         (self.op.clean_irq_event)(self.data, event);
     }
@@ -100,22 +293,84 @@ impl Uart {
     }
 }
 
-#[derive(Debug, Clone, Copy, Default)]
-pub struct IrqEvent {
+bitflags::bitflags! {
+    /// Pending UART interrupts.
+    ///
+    /// This used to be a struct with `rx` and `tx` fields. Read them with
+    /// [`IrqEvent::rx`] and [`IrqEvent::tx`] instead, and build events from
+    /// the flags, e.g. `IrqEvent::RX | IrqEvent::TX`.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct IrqEvent: u32 {
+        /// Received data available.
+        const RX = 1 << 0;
+        /// Transmitter ready for more data.
+        const TX = 1 << 1;
+        /// Data sits in the RX FIFO below the trigger level.
+        const RX_TIMEOUT = 1 << 2;
+        /// Received data was lost.
+        const OVERRUN = 1 << 3;
+        const PARITY = 1 << 4;
+        const FRAMING = 1 << 5;
+        /// The line was held low for longer than a frame.
+        const BREAK = 1 << 6;
+        /// A modem status line like CTS changed.
+        const MODEM_STATUS = 1 << 7;
+        /// DesignWare 8250: LCR was written while the UART was busy.
+        const BUSY_DETECT = 1 << 8;
+
+        /// Any receive error or break.
+        const LINE_STATUS = Self::OVERRUN.bits()
+            | Self::PARITY.bits()
+            | Self::FRAMING.bits()
+            | Self::BREAK.bits();
+    }
+}
+
+impl IrqEvent {
+    /// Received data available, see [`IrqEvent::RX`].
+    pub fn rx(&self) -> bool {
+        self.contains(Self::RX)
+    }
+
+    /// Transmitter ready for more data, see [`IrqEvent::TX`].
+    pub fn tx(&self) -> bool {
+        self.contains(Self::TX)
+    }
+}
+
+/// Which interrupts a UART raises.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IrqMask {
+    /// Received data available.
     pub rx: bool,
+    /// Transmitter ready for more data. Enable it only while there is data
+    /// to send, or it fires continuously.
     pub tx: bool,
+    /// Data sits in the RX FIFO below the trigger level. Some UARTs can only
+    /// enable this together with `rx`.
+    pub rx_timeout: bool,
+    /// Overrun, parity, framing error or break.
+    pub line_status: bool,
+    /// A modem status line like CTS changed.
+    pub modem_status: bool,
 }
 
+/// Driver vtable, built from a [`Console`] implementation.
 #[derive(Clone, Copy)]
-struct UartOp {
+pub struct UartOp {
     can_put: fn(UartData) -> bool,
     put: fn(UartData, u8) -> Result<(), ErrorKind>,
     can_get: fn(UartData) -> bool,
     get: fn(UartData) -> Result<u8, ErrorKind>,
     set_irq_enable: fn(UartData, bool),
     get_irq_enable: fn(UartData) -> bool,
+    set_irq_mask: fn(UartData, IrqMask),
+    get_irq_mask: fn(UartData) -> IrqMask,
     get_irq_event: fn(UartData) -> IrqEvent,
     clean_irq_event: fn(UartData, IrqEvent),
+    set_config: fn(UartData, &Config) -> Result<(), ConfigError>,
+    enable_fifo: fn(UartData, &FifoConfig) -> usize,
+    init: fn(UartData, &Config, &FifoConfig) -> Result<usize, ConfigError>,
 }
 
 pub struct Sender {
@@ -150,9 +405,30 @@ impl Sender {
         (self.op.put)(self.uart, word)
     }
 
+    /// Write up to [`UartData::fifo_depth`] bytes of `buf` after a single
+    /// readiness check, and return how many were written.
+    pub fn write_fifo(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if !self.can_write() {
+            return Err(Error::WouldBlock);
+        }
+        fence(Ordering::Release);
+
+        let len = buf.len().min(self.uart.fifo_depth);
+        for &word in &buf[..len] {
+            unsafe { self.write_uncheck(word)? };
+        }
+        Ok(len)
+    }
+
     pub fn write_str_blocking(&mut self, s: &str) -> core::fmt::Result {
-        for c in s.bytes() {
-            let _ = block!(self.write(c));
+        let mut buf = s.as_bytes();
+        while !buf.is_empty() {
+            // Skip a byte the driver failed to write.
+            let len = block!(self.write_fifo(buf)).unwrap_or(1);
+            buf = &buf[len..];
         }
         Ok(())
     }
@@ -195,7 +471,11 @@ impl Receiver {
     }
 }
 
-pub(crate) trait Console {
+/// A UART driver.
+///
+/// Implement it for an out-of-tree UART and list the compatibles it handles
+/// with [`OfMatch::new`], then pass them to [`init_with_drivers`].
+pub trait Console {
     fn can_put(uart: UartData) -> bool;
     fn put(uart: UartData, c: u8) -> Result<(), ErrorKind>;
     fn can_get(uart: UartData) -> bool;
@@ -205,36 +485,131 @@ pub(crate) trait Console {
     fn get_irq_event(uart: UartData) -> IrqEvent;
     fn clean_irq_event(uart: UartData, event: IrqEvent);
 
-    fn to_op() -> UartOp {
-        UartOp {
-            can_put: Self::can_put,
-            put: Self::put,
-            can_get: Self::can_get,
-            get: Self::get,
-            set_irq_enable: Self::set_irq_enable,
-            get_irq_enable: Self::get_irq_enable,
-            get_irq_event: Self::get_irq_event,
-            clean_irq_event: Self::clean_irq_event,
+    fn set_config(_uart: UartData, _config: &Config) -> Result<(), ConfigError> {
+        Err(ConfigError::Unsupported)
+    }
+
+    /// Defaults to [`Console::set_irq_enable`] with `rx || tx`.
+    fn set_irq_mask(uart: UartData, mask: IrqMask) {
+        Self::set_irq_enable(uart, mask.rx || mask.tx);
+    }
+
+    /// Defaults to `rx` and `tx` from [`Console::get_irq_enable`].
+    fn get_irq_mask(uart: UartData) -> IrqMask {
+        let enable = Self::get_irq_enable(uart);
+        IrqMask {
+            rx: enable,
+            tx: enable,
+            ..Default::default()
         }
     }
+
+    /// Enable the FIFOs and return how many bytes may be written once
+    /// [`Console::can_put`] is true. Defaults to `1`, no FIFO.
+    fn enable_fifo(_uart: UartData, _fifo: &FifoConfig) -> usize {
+        1
+    }
+
+    /// Defaults to [`Console::set_config`], then [`Console::enable_fifo`].
+    fn init(uart: UartData, config: &Config, fifo: &FifoConfig) -> Result<usize, ConfigError> {
+        Self::set_config(uart, config)?;
+        Ok(Self::enable_fifo(uart, fifo))
+    }
+}
+
+/// Builds the vtable of a [`Console`], out of reach of implementers.
+pub(crate) trait ConsoleOp: Console {
+    const OP: UartOp = UartOp {
+        can_put: Self::can_put,
+        put: Self::put,
+        can_get: Self::can_get,
+        get: Self::get,
+        set_irq_enable: Self::set_irq_enable,
+        get_irq_enable: Self::get_irq_enable,
+        set_irq_mask: Self::set_irq_mask,
+        get_irq_mask: Self::get_irq_mask,
+        get_irq_event: Self::get_irq_event,
+        clean_irq_event: Self::clean_irq_event,
+        set_config: Self::set_config,
+        enable_fifo: Self::enable_fifo,
+        init: Self::init,
+    };
+
+    fn to_op() -> UartOp {
+        Self::OP
+    }
 }
 
+impl<T: Console> ConsoleOp for T {}
+
+/// Register access information handed to every [`Console`] call.
 #[derive(Clone, Copy)]
-pub(crate) struct UartData {
-    pub base: usize,
-    pub io_kind: IoKind,
+pub struct UartData {
+    pub(crate) base: usize,
+    pub(crate) io_kind: IoKind,
+    /// Register `n` is located at `base + (n << reg_shift)`.
+    pub(crate) reg_shift: u8,
+    pub(crate) quirks: Quirks,
+    /// Reference clock in Hz, `0` if unknown.
+    pub(crate) clock: u32,
+    /// Bytes that may be written once `can_put` is true.
+    pub(crate) fifo_depth: usize,
 }
 
 impl UartData {
-    fn new(base: u64, io_kind: IoKind, f: FnPhysToVirt) -> Self {
-        let mmio = f(base as _);
+    fn new(base: u64, io_kind: IoKind, reg_shift: u8, f: FnPhysToVirt) -> Self {
+        let base = match io_kind {
+            IoKind::Port => base as usize,
+            _ => f(base as _) as usize,
+        };
+
+        Self::from_virt(base, io_kind, reg_shift)
+    }
 
+    const fn from_virt(base: usize, io_kind: IoKind, reg_shift: u8) -> Self {
         Self {
-            base: mmio as _,
+            base,
             io_kind,
+            reg_shift,
+            quirks: Quirks::empty(),
+            clock: 0,
+            fifo_depth: 1,
         }
     }
 
+    /// Virtual base address, or the port number for [`IoKind::Port`].
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    pub fn io_kind(&self) -> IoKind {
+        self.io_kind
+    }
+
+    pub fn reg_shift(&self) -> u8 {
+        self.reg_shift
+    }
+
+    pub fn quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    /// Reference clock in Hz, `0` if unknown.
+    pub fn clock(&self) -> u32 {
+        self.clock
+    }
+
+    /// Bytes that may be written once [`Console::can_put`] is true, `1`
+    /// until [`Uart::enable_fifo`].
+    pub fn fifo_depth(&self) -> usize {
+        self.fifo_depth
+    }
+
+    /// Address of register `reg` with `reg_shift` applied.
+    pub fn reg_addr(&self, reg: usize) -> usize {
+        self.base + (reg << self.reg_shift)
+    }
+
     pub fn reg_u8(&self, offset: usize) -> *mut u8 {
         self.reg(offset)
     }
@@ -247,58 +622,156 @@ impl UartData {
     }
 }
 
-pub fn init(fdt_addr: NonNull<u8>, fn_phys_to_virt: FnPhysToVirt) -> Option<Uart> {
-    let fdt = Fdt::from_ptr(fdt_addr).ok()?;
+/// The device tree blob at `fdt_addr`, sized by its header, to pass to
+/// [`init`] and friends.
+///
+/// `None` if there is no valid header at `fdt_addr`.
+///
+/// # Safety
+///
+/// `fdt_addr` must point to a mapped device tree blob that stays valid and
+/// unchanged for `'a`.
+pub unsafe fn fdt_from_ptr<'a>(fdt_addr: NonNull<u8>) -> Option<&'a [u8]> {
+    Fdt::from_ptr(fdt_addr).ok().map(|fdt| fdt.as_slice())
+}
 
-    let chosen = fdt.chosen()?;
+pub fn init<'a>(fdt: &'a [u8], fn_phys_to_virt: FnPhysToVirt) -> Result<Uart, ProbeError<'a>> {
+    init_with_drivers(fdt, fn_phys_to_virt, &[])
+}
 
-    let mut io_kind = IoKind::Mmio32;
-    let node;
-    let mut is_8250 = false;
+/// Like [`init`], but `drivers` are tried before [`OF_MATCH_TABLE`].
+pub fn init_with_drivers<'a>(
+    fdt: &'a [u8],
+    fn_phys_to_virt: FnPhysToVirt,
+    drivers: &[OfMatch],
+) -> Result<Uart, ProbeError<'a>> {
+    let fdt = Fdt::from_bytes(fdt).map_err(|_| ProbeError::InvalidFdt)?;
+
+    let chosen = fdt
+        .find_nodes("/chosen")
+        .next()
+        .ok_or(ProbeError::NoChosen)?;
+    let bootargs = chosen.find_property("bootargs").map(|p| p.str());
+
+    // Like Linux: an explicit `earlycon=<name>,<addr>` wins, while bare
+    // `earlycon` (or none at all) means `/chosen/stdout-path`. So does one
+    // we can't drive, like `earlycon=sbi`.
+    let earlycon_err = match bootargs.and_then(Earlycon::from_bootargs) {
+        Some(Ok(Earlycon::Uart(earlycon))) => {
+            if let Some(mut uart) = earlycon.uart(fn_phys_to_virt) {
+                if earlycon.io_kind != IoKind::Port
+                    && let Some(addr) = earlycon.addr
+                    && let Some(node) = fdt_find_node_by_addr(&fdt, addr, drivers)
+                {
+                    uart.set_input_clock(fdt_input_clock(&node).unwrap_or(0));
+                    uart.irq = fdt_irq(&node);
+                }
 
-    match chosen.stdout() {
-        Some(n) => node = n.node,
-        None => {
-            let (n, io) = fdt_bootargs_find_node(&chosen, &fdt)?;
-            node = n;
-            io_kind = io;
-            is_8250 = true;
+                return Ok(uart);
+            }
+            None
         }
+        Some(Ok(Earlycon::Sbi)) => Some(EarlyconError::UnsupportedName("sbi")),
+        Some(Err(e)) => Some(e),
+        Some(Ok(Earlycon::StdoutPath)) | None => None,
     };
 
-    let reg = node.reg()?.next()?;
-
-    let uart = UartData::new(reg.address, io_kind, fn_phys_to_virt);
-
-    if is_8250 {
-        return Some(Uart::_new::<Ns16550>(uart));
-    } else {
-        for c in node.compatibles() {
-            macro_rules! of_uart {
-                ($name:ty, $compatible:expr) => {
-                    for want in $compatible {
-                        if c.contains(want) {
-                            return Some(Uart::_new::<$name>(uart));
-                        }
-                    }
-                };
-            }
+    let stdout_path = chosen
+        .find_property("stdout-path")
+        .ok_or(earlycon_err.map_or(ProbeError::NoStdoutPath, ProbeError::BadEarlycon))?
+        .str();
+    let (path, options) = match stdout_path.split_once(':') {
+        Some((path, options)) => (path, Some(options)),
+        None => (stdout_path, None),
+    };
 
-            of_uart!(AuxMini, ["brcm,bcm2835-aux-uart"]);
-            of_uart!(Pl011, ["arm,pl011", "arm,primecell"]);
-            of_uart!(Ns16550, ["snps,dw-apb-uart"]);
-        }
+    // The firmware chose this console, so use it even if disabled.
+    let node = fdt_find_path(&fdt, path)?;
+    let mut uart = Uart::new_by_fdt_node_any_status(&node, fn_phys_to_virt, drivers)?;
+    uart.boot_config = options.and_then(Config::parse_options);
+    Ok(uart)
+}
+
+/// Every UART in the device tree with a supported compatible, in tree
+/// order, skipping `status = "disabled"` nodes.
+///
+/// Yields nothing if the device tree is invalid. Use [`serial_alias`] to
+/// tell the ports apart by their `/aliases` name.
+pub fn enumerate<'a>(
+    fdt: &'a [u8],
+    fn_phys_to_virt: FnPhysToVirt,
+) -> impl Iterator<Item = (Node<'a>, Uart)> {
+    Fdt::from_bytes(fdt)
+        .ok()
+        .into_iter()
+        .flat_map(|fdt| fdt.all_nodes())
+        // `arm,primecell` alone is any AMBA device, e.g. a PL330 DMA.
+        .filter(|node| of_match(node).is_some_and(|m| m.compatible != "arm,primecell"))
+        .filter_map(move |node| {
+            let uart = Uart::new_by_fdt_node(&node, fn_phys_to_virt).ok()?;
+            Some((node, uart))
+        })
+}
+
+/// Create the console UART from a kernel command line alone, without a
+/// device tree.
+///
+/// An `earlycon=` with an address is used first, then the last `console=`
+/// that names a UART by address, see [`EarlyconUart::from_console`].
+/// Either is skipped if its [`IoKind`] is not supported on this target.
+pub fn init_from_cmdline(cmdline: &str, fn_phys_to_virt: FnPhysToVirt) -> Option<Uart> {
+    if let Some(Ok(Earlycon::Uart(earlycon))) = Earlycon::from_bootargs(cmdline)
+        && earlycon.addr.is_some()
+        && earlycon.io_kind.is_supported()
+    {
+        return earlycon.uart(fn_phys_to_virt);
     }
 
-    None
+    cmdline
+        .split_ascii_whitespace()
+        .filter_map(|arg| arg.strip_prefix("console="))
+        .filter_map(EarlyconUart::from_console)
+        .rfind(|console| console.io_kind.is_supported())?
+        .uart(fn_phys_to_virt)
 }
 
-#[derive(Clone, Copy)]
+/// Create the console UART from the ACPI SPCR table in `spcr`.
+///
+/// See [`Spcr::uart`].
+pub fn init_from_spcr(spcr: &[u8], fn_phys_to_virt: FnPhysToVirt) -> Option<Uart> {
+    Spcr::from_bytes(spcr)?.uart(fn_phys_to_virt)
+}
+
+bitflags::bitflags! {
+    /// Register level differences between 8250 compatible variants.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Quirks: u32 {
+        /// 8250/16450, or 16550 with the broken FIFO: never enable FIFOs.
+        const NO_FIFO = 1 << 0;
+        /// 16750 64-byte FIFO, enabled with FCR bit 5.
+        const FIFO64 = 1 << 1;
+        /// XScale/PXA/MMP: IER bit 6 (UUE) enables the unit and must stay set.
+        const XSCALE_UUE = 1 << 2;
+        /// IER bit 4 enables the receiver timeout interrupt.
+        const RTOIE = 1 << 3;
+        /// TI DA8xx: TX/RX are held in reset until released by PWREMU_MGMT.
+        const DA830_PWREMU = 1 << 4;
+        /// Synopsys DesignWare APB UART, with USR/CPR registers and busy detect.
+        const DW_APB = 1 << 5;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum IoKind {
+    /// 8-bit port I/O.
     Port,
+    /// 8-bit MMIO.
     Mmio,
+    /// 16-bit MMIO.
     Mmio16,
+    /// 32-bit MMIO.
     Mmio32,
+    /// 32-bit big-endian MMIO.
     Mmio32be,
 }
 
@@ -306,17 +779,32 @@ impl IoKind {
     pub fn width(&self) -> usize {
         match self {
             IoKind::Port => 1,
-            IoKind::Mmio => 4,
+            IoKind::Mmio => 1,
             IoKind::Mmio16 => 2,
             IoKind::Mmio32 => 4,
             IoKind::Mmio32be => 4,
         }
     }
-}
 
-impl From<&str> for IoKind {
-    fn from(value: &str) -> Self {
-        match value {
+    /// Port I/O is only available on x86_64.
+    pub fn is_supported(&self) -> bool {
+        *self != IoKind::Port || cfg!(target_arch = "x86_64")
+    }
+
+    /// Register shift implied by the access width, as Linux earlycon does.
+    pub fn default_reg_shift(&self) -> u8 {
+        match self {
+            IoKind::Port | IoKind::Mmio => 0,
+            IoKind::Mmio16 => 1,
+            IoKind::Mmio32 | IoKind::Mmio32be => 2,
+        }
+    }
+
+    /// Parse an earlycon io type: `io`, `mmio`, `mmio16`, `mmio32`,
+    /// `mmio32be` or `mmio32native`.
+    pub fn parse(value: &str) -> Option<Self> {
+        Some(match value {
+            "io" => IoKind::Port,
             "mmio" => IoKind::Mmio,
             "mmio16" => IoKind::Mmio16,
             "mmio32" => IoKind::Mmio32,
@@ -328,63 +816,424 @@ impl From<&str> for IoKind {
                     IoKind::Mmio32be
                 }
             }
-            _ => IoKind::Port,
-        }
+            _ => return None,
+        })
+    }
+
+    /// Access kind for a `reg-io-width` value.
+    pub fn from_reg_io_width(width: u32, big_endian: bool) -> Option<Self> {
+        Some(match width {
+            1 => IoKind::Mmio,
+            2 => IoKind::Mmio16,
+            4 if big_endian => IoKind::Mmio32be,
+            4 => IoKind::Mmio32,
+            _ => return None,
+        })
+    }
+}
+
+impl From<&str> for IoKind {
+    fn from(value: &str) -> Self {
+        IoKind::parse(value).unwrap_or(IoKind::Port)
     }
 }
 
-fn fdt_bootargs_find_node<'a>(chosen: &Chosen<'a>, fdt: &'a Fdt<'a>) -> Option<(Node<'a>, IoKind)> {
-    let bootargs = chosen.bootargs()?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EARLY: Uart = Uart::new_pl011(0x900_0000);
 
-    let earlycon = bootargs
-        .split_ascii_whitespace()
-        .find(|&arg| arg.contains("earlycon"))?;
+    #[test]
+    fn test_const_new() {
+        assert_eq!(EARLY.tx.as_ref().unwrap().mmio(), 0x900_0000);
+    }
 
-    let mut tmp = earlycon.split('=');
-    let _ = tmp.next()?;
-    let values = tmp.next()?;
+    #[test]
+    fn test_set_config_ns16550() {
+        let mut regs = [0u32; 8];
+        regs[5] = 1 << 6; // LSR.TEMT
+        let mut uart = Uart::new_ns16550_mmio(regs.as_mut_ptr() as usize, IoKind::Mmio32, 2);
+
+        assert_eq!(
+            uart.set_config(Config::default()),
+            Err(ConfigError::UnknownClock)
+        );
+
+        uart.set_input_clock(24_000_000);
+        uart.set_config(Config::default()).unwrap();
+        assert_eq!(regs[0], 13); // DLL
+        assert_eq!(regs[1], 0); // DLM
+        assert_eq!(regs[3], 0b11); // LCR: 8N1
+
+        // DesignWare stuck busy, as with unread RX data: retried, not hung.
+        let mut regs = [0u32; 32];
+        regs[2] = 0xc1; // IIR: FIFOs enabled
+        regs[5] = 1 << 6; // LSR.TEMT
+        regs[31] = 1; // USR.BUSY
+        let mut uart = Uart::new_dw_apb(regs.as_mut_ptr() as usize);
+        uart.set_input_clock(24_000_000);
+        uart.set_config(Config::default()).unwrap();
+        assert_eq!(regs[2], 0b111); // FCR: enabled, RX and TX cleared
+    }
 
-    let mut values = values.split(',');
+    #[test]
+    fn test_enable_fifo_ns16550() {
+        let mut regs = [0u32; 64];
+        regs[5] = 1 << 5; // LSR.THRE
+        regs[61] = 2 << 16; // CPR: 32-byte FIFO
+        let mut uart = Uart::new_dw_apb(regs.as_mut_ptr() as usize);
+        assert_eq!(uart.enable_fifo(), 32);
+        assert_eq!(regs[2], 0x87); // FCR
+
+        let buf = [b'x'; 40];
+        let tx = uart.tx.as_mut().unwrap();
+        assert_eq!(tx.write_fifo(&buf), Ok(32));
+        assert_eq!(regs[0], b'x' as u32);
+
+        // The original 16550
+        let mut regs = [0xffu32; 8];
+        let mut uart = Uart::new_ns16550_mmio(regs.as_mut_ptr() as usize, IoKind::Mmio32, 2);
+        assert_eq!(uart.enable_fifo(), 1);
+        assert_eq!(regs[2], 0); // FCR
+    }
 
-    let name = values.next()?;
+    #[test]
+    fn test_set_config_pl011() {
+        let mut regs = [0u32; 32];
+        let mut uart = Uart::new_pl011(regs.as_mut_ptr() as usize);
+        uart.set_input_clock(24_000_000);
+        uart.set_config(Config {
+            parity: Parity::Even,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(regs[0x24 / 4], 13); // IBRD
+        assert_eq!(regs[0x28 / 4], 1); // FBRD
+        assert_eq!(regs[0x2C / 4], 0b11 << 5 | 1 << 2 | 1 << 1); // LCR_H: 8E1
+        assert_eq!(regs[0x30 / 4], 0x301); // CR: UARTEN | TXE | RXE
+
+        // Data stuck in the TX FIFO of a disabled UART keeps BUSY set.
+        let mut regs = [0u32; 32];
+        regs[0x18 / 4] = 1 << 3; // FR: BUSY
+        regs[0x2C / 4] = 1 << 4; // LCR_H: FEN
+        let mut uart = Uart::new_pl011(regs.as_mut_ptr() as usize);
+        uart.set_input_clock(24_000_000);
+        uart.set_config(Config::default()).unwrap();
+        assert_eq!(regs[0x2C / 4], 0b111 << 4); // LCR_H: 8-bit, FEN kept
+    }
 
-    if !name.contains("uart") {
-        return None;
+    #[test]
+    fn test_init_pl011() {
+        let mut regs = [0u32; 32];
+        regs[0x038 / 4] = 0x10; // IMSC: RX
+        let mut uart = Uart::new_pl011(regs.as_mut_ptr() as usize);
+        uart.set_input_clock(24_000_000);
+
+        let fifo = FifoConfig {
+            rx_level: FifoLevel::SevenEighths,
+            tx_level: FifoLevel::OneEighth,
+        };
+        assert_eq!(uart.init(Config::default(), fifo), Ok(1));
+        assert_eq!(regs[0x024 / 4], 13); // IBRD
+        assert_eq!(regs[0x028 / 4], 1); // FBRD
+        assert_eq!(regs[0x02C / 4], 0x70); // LCR_H: 8-bit, FIFOs
+        assert_eq!(regs[0x034 / 4], 0b100_000); // IFLS
+        assert_eq!(regs[0x038 / 4], 0); // IMSC
+        assert_eq!(regs[0x044 / 4], 0x7ff); // ICR
+        assert_eq!(regs[0x030 / 4], 0x301); // CR: RXE, TXE, UARTEN
     }
 
-    let param2 = values.next()?;
-    let addr_str;
-    let io_kind = if param2.contains("0x") {
-        addr_str = param2;
-        IoKind::Mmio
-    } else {
-        addr_str = values.next()?;
-        IoKind::from(param2)
-    };
+    #[test]
+    fn test_init_pl011_busy() {
+        use core::sync::atomic::AtomicU32;
+        use std::{thread, time::Duration};
+
+        const FR: usize = 0x018 / 4;
+        const CR: usize = 0x030 / 4;
+
+        let regs: [AtomicU32; 32] = Default::default();
+        regs[FR].store(1 << 3, Ordering::SeqCst); // BUSY
+        regs[CR].store(0x301, Ordering::SeqCst); // RXE, TXE, UARTEN
+        let mut uart = Uart::new_pl011(regs.as_ptr() as usize);
+        uart.set_input_clock(24_000_000);
+
+        // Plays the transmitter: finishes sending, noting whether the UART
+        // was still enabled while it did.
+        let enabled_while_busy = thread::scope(|s| {
+            let tx = s.spawn(|| {
+                thread::sleep(Duration::from_millis(20));
+                let cr = regs[CR].load(Ordering::SeqCst);
+                regs[FR].store(0, Ordering::SeqCst);
+                cr & 1 != 0
+            });
+            assert_eq!(uart.init(Config::default(), FifoConfig::default()), Ok(1));
+            tx.join().unwrap()
+        });
+        assert!(enabled_while_busy);
+        assert_eq!(regs[CR].load(Ordering::SeqCst), 0x301);
+    }
 
-    let mmio = u64::from_str_radix(addr_str.trim_start_matches("0x"), 16).ok()?;
+    #[test]
+    fn test_irq_mask() {
+        let mut regs = [0u32; 32];
+        let mut uart = Uart::new_pl011(regs.as_mut_ptr() as usize);
+        let mask = IrqMask {
+            rx: true,
+            rx_timeout: true,
+            line_status: true,
+            ..Default::default()
+        };
+        uart.set_irq_mask(mask);
+        assert_eq!(regs[0x38 / 4], 0x7d0); // IMSC: OE|BE|PE|FE|RT|RX
+        assert_eq!(uart.get_irq_mask(), mask);
+
+        let mut regs = [0u32; 8];
+        let mut uart = Uart::new_dw_apb(regs.as_mut_ptr() as usize);
+        uart.set_irq_mask(IrqMask {
+            tx: true,
+            modem_status: true,
+            ..Default::default()
+        });
+        assert_eq!(regs[1], 0b1010); // IER: MSI|THRI
+        uart.set_irq_mask(IrqMask {
+            rx_timeout: true,
+            ..Default::default()
+        });
+        assert_eq!(regs[1], 0b1); // IER: RDI
+        assert!(uart.get_irq_mask().rx);
+    }
 
-    for node in fdt.all_nodes() {
-        if let Some(regs) = node.reg() {
-            for reg in regs {
-                if reg.address.eq(&mmio) {
-                    return Some((node, io_kind));
-                }
-            }
-        }
+    #[test]
+    fn test_irq_event() {
+        let mut regs = [0u32; 32];
+        regs[0x3C / 4] = 0x7f0; // RIS: OE|BE|PE|FE|RT|TX|RX
+        regs[0x40 / 4] = 0x450; // MIS: OE|RT|RX
+        let mut uart = Uart::new_pl011(regs.as_mut_ptr() as usize);
+        let event = uart.get_irq_event();
+        assert_eq!(
+            event,
+            IrqEvent::RX | IrqEvent::RX_TIMEOUT | IrqEvent::OVERRUN
+        );
+        assert!(event.rx() && !event.tx());
+        uart.clean_irq_event(event);
+        assert_eq!(regs[0x44 / 4], 0x450); // ICR
+
+        let ns16550 = |iir: u32, lsr: u32| {
+            let mut regs = [0u32; 8];
+            regs[2] = iir;
+            regs[5] = lsr;
+            Uart::new_dw_apb(regs.as_mut_ptr() as usize).get_irq_event()
+        };
+        assert_eq!(ns16550(0xc1, 0), IrqEvent::empty()); // FIFOs, no interrupt
+        assert_eq!(ns16550(0xcc, 0), IrqEvent::RX_TIMEOUT);
+        assert_eq!(ns16550(0xc4, 0), IrqEvent::RX);
+        assert_eq!(ns16550(0xc2, 0), IrqEvent::TX);
+        assert_eq!(ns16550(0xc0, 0), IrqEvent::MODEM_STATUS);
+        assert_eq!(ns16550(0xc7, 0), IrqEvent::BUSY_DETECT);
+        assert_eq!(
+            ns16550(0xc6, 1 << 1 | 1 << 3), // LSR: OE|FE
+            IrqEvent::OVERRUN | IrqEvent::FRAMING
+        );
     }
 
-    None
-}
+    #[test]
+    fn test_irq_aux_mini() {
+        let mut regs = [0u32; 16];
+        regs[2] = 0xc4; // IIR: FIFOs, RX
+        let mut uart = Uart::new_aux_mini(regs.as_mut_ptr() as usize);
+        uart.set_irq_mask(IrqMask {
+            rx: true,
+            ..Default::default()
+        });
+        assert_eq!(uart.get_irq_event(), IrqEvent::RX);
+        uart.clean_irq_event(IrqEvent::RX);
+        assert_eq!(regs[1], 0b1101); // IER: RX, with bits 3:2 for the erratum
+        assert_eq!(regs[2], 0xc4); // IIR untouched
+
+        let mask = uart.get_irq_mask();
+        assert!(mask.rx && !mask.tx && !mask.line_status);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_set_config_aux_mini() {
+        // The AUX block, with the mini UART at 0x40.
+        let mut regs = [0u32; 32];
+        regs[0x04 / 4] = 1 << 1; // AUX_ENABLES: SPI1
+        let mut uart = Uart::new_aux_mini(regs[0x40 / 4..].as_mut_ptr() as usize);
+        uart.set_input_clock(250_000_000);
+        uart.set_config(Config {
+            flow_control: true,
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(regs[0x04 / 4], 0b11); // AUX_ENABLES: SPI1 | mini UART
+        assert_eq!(regs[0x4C / 4], 0b11); // LCR: 8-bit
+        assert_eq!(regs[0x60 / 4], 0b1111); // CNTL: RX, TX, RTS/CTS flow
+        assert_eq!(regs[0x68 / 4], 270); // BAUD
+
+        // Running, with the transmitter done.
+        regs[0x64 / 4] = 1 << 9; // STAT: TX done
+        uart.set_config(Config::default()).unwrap();
+        assert_eq!(regs[0x60 / 4], 0b11); // CNTL: RX, TX
+
+        assert_eq!(
+            uart.set_config(Config {
+                parity: Parity::Even,
+                ..Default::default()
+            }),
+            Err(ConfigError::Unsupported)
+        );
+    }
 
     #[test]
     fn test_uart_init() {
         let fdt = include_bytes!("../../dtb/rk3568-firefly-roc-pc-se.dtb");
-        let fdt_addr = NonNull::new(fdt.as_ptr() as usize as _).unwrap();
-        let _ = init(fdt_addr, |r| r as _).unwrap();
+        let uart = init(fdt, |r| r as _).unwrap();
+        assert_eq!(uart.tx.as_ref().unwrap().mmio(), 0xfe66_0000);
+    }
+
+    #[test]
+    fn test_enumerate() {
+        let fdt = include_bytes!("../../dtb/rk3568-firefly-roc-pc-se.dtb");
+        let mut uarts = enumerate(fdt, |r| r as _);
+
+        let (node, uart) = uarts.next().unwrap();
+        assert_eq!(serial_alias(&node), Some("serial3"));
+        assert_eq!(uart.data.base(), 0xfe67_0000);
+        // serial2 at 0xfe660000 is disabled.
+        assert_eq!(
+            uarts
+                .map(|(node, _)| serial_alias(&node).unwrap())
+                .collect::<Vec<_>>(),
+            ["serial4", "serial7", "serial8", "serial9"]
+        );
+
+        let (_, uart) = enumerate(fdt, |r| r as _)
+            .find(|(node, _)| serial_alias(node) == Some("serial8"))
+            .unwrap();
+        assert_eq!(uart.data.base(), 0xfe6c_0000);
+    }
+
+    #[test]
+    fn test_new_by_alias() {
+        let fdt = include_bytes!("../../dtb/rk3568-firefly-roc-pc-se.dtb");
+
+        let uart = Uart::new_by_alias(fdt, "serial4", |r| r as _).unwrap();
+        assert_eq!(uart.data.base(), 0xfe68_0000);
+        assert_eq!(uart.data.reg_shift(), 2);
+
+        assert_eq!(
+            Uart::new_by_alias(fdt, "serial10", |r| r as _).err(),
+            Some(ProbeError::NoAlias("serial10"))
+        );
+        assert!(matches!(
+            Uart::new_by_alias(fdt, "i2c0", |r| r as _),
+            Err(ProbeError::UnsupportedCompatible(_))
+        ));
+        assert_eq!(
+            Uart::new_by_alias(fdt, "serial2", |r| r as _).err(),
+            Some(ProbeError::Disabled)
+        );
+
+        let fdt = include_bytes!("../../dtb/test-board.dtb");
+        let uart = Uart::new_by_alias(fdt, "serial3", |r| r as _).unwrap();
+        assert_eq!(uart.data.base(), 0x4010_3000);
+        // Inherited from the root `interrupt-parent`.
+        assert_eq!(uart.irq().unwrap().parent, 3);
+        assert_eq!(
+            Uart::new_by_alias(fdt, "serial4", |r| r as _).err(),
+            Some(ProbeError::Disabled)
+        );
+    }
+
+    #[test]
+    fn test_init_errors() {
+        let fdt = include_bytes!("../../dtb/test-board.dtb");
+        let mut bad = *fdt;
+        bad[0] = 0;
+        assert_eq!(init(&bad, |r| r as _).err(), Some(ProbeError::InvalidFdt));
+
+        // The rk3568 tree with `earlycon=` renamed and no `stdout-path`.
+        let mut fdt = *include_bytes!("../../dtb/rk3568-firefly-roc-pc-se.dtb");
+        let pos = fdt.windows(9).position(|w| w == b"earlycon=").unwrap();
+        fdt[pos..pos + 9].copy_from_slice(b"earlycom=");
+        assert_eq!(init(&fdt, |r| r as _).err(), Some(ProbeError::NoStdoutPath));
+
+        fdt[pos..pos + 15].copy_from_slice(b"earlycon=foo,0x");
+        assert_eq!(
+            init(&fdt, |r| r as _).err(),
+            Some(ProbeError::BadEarlycon(EarlyconError::UnsupportedName(
+                "foo"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_init_earlycon_falls_back_to_stdout() {
+        let fdt = *include_bytes!("../../dtb/test-board.dtb");
+        let bootargs = b"console=ttyS0,115200 earlycon";
+        let pos = fdt
+            .windows(bootargs.len())
+            .position(|w| w == bootargs)
+            .unwrap();
+
+        for earlycon in ["earlycon=sbi", "earlycon=bogus,0x1000"] {
+            let mut fdt = fdt;
+            fdt[pos..pos + bootargs.len()].fill(b' ');
+            fdt[pos..pos + earlycon.len()].copy_from_slice(earlycon.as_bytes());
+            let uart = init(&fdt, |r| r as _).unwrap();
+            assert_eq!(uart.tx.as_ref().unwrap().mmio(), 0x1000_0000, "{earlycon}");
+        }
+    }
+
+    #[test]
+    fn test_init_earlycon_overrides_stdout() {
+        let fdt = include_bytes!("../../dtb/test-board-earlycon.dtb");
+        let uart = init(fdt, |r| r as _).unwrap();
+        assert_eq!(uart.tx.as_ref().unwrap().mmio(), 0x1000_1000);
+        assert_eq!(uart.input_clock(), 24_000_000);
+        assert_eq!(uart.boot_config().unwrap().baud, 1_500_000);
+    }
+
+    #[test]
+    fn test_init_from_cmdline() {
+        let uart = init_from_cmdline(
+            "console=tty0 console=uart8250,mmio32,0x10000000,115200n8 quiet",
+            |r| r as _,
+        )
+        .unwrap();
+        assert_eq!(uart.tx.as_ref().unwrap().mmio(), 0x1000_0000);
+        assert_eq!(uart.boot_config(), Some(Config::default()));
+
+        let uart = init_from_cmdline("earlycon=pl011,0x9000000 console=ttyAMA0,115200", |r| {
+            r as _
+        })
+        .unwrap();
+        assert_eq!(uart.tx.as_ref().unwrap().mmio(), 0x900_0000);
+
+        assert!(init_from_cmdline("console=tty0 earlycon", |r| r as _).is_none());
+
+        // Port I/O only exists on x86_64.
+        let uart = init_from_cmdline(
+            "earlycon=uart8250,io,0x3f8 console=uart8250,mmio32,0x10000000",
+            |r| r as _,
+        )
+        .unwrap();
+        let port = cfg!(target_arch = "x86_64");
+        let mmio = if port { 0x3f8 } else { 0x1000_0000 };
+        assert_eq!(uart.tx.as_ref().unwrap().mmio(), mmio);
+        assert_eq!(
+            init_from_cmdline("console=uart8250,io,0x3f8", |r| r as _).is_some(),
+            port
+        );
+    }
+
+    #[test]
+    fn test_init_stdout_options() {
+        let fdt = include_bytes!("../../dtb/test-board.dtb");
+        let uart = init(fdt, |r| r as _).unwrap();
+        assert_eq!(uart.boot_config(), Some(Config::default()));
+        assert_eq!(uart.input_clock(), 3_686_400);
     }
 }
@@ -1,59 +1,182 @@
 use cfg_if::cfg_if;
 
-use crate::{Console, ErrorKind, IoKind, IrqEvent, UartData};
+use crate::{
+    Config, ConfigError, Console, DataBits, ErrorKind, FifoConfig, FifoLevel, IoKind, IrqEvent,
+    IrqMask, Parity, Quirks, StopBits, UartData,
+};
+
+/// Receive buffer, read.
+const RBR: usize = 0;
+/// Transmit holding, write.
+const THR: usize = 0;
+/// Divisor latch, low and high byte, with `LCR_DLAB` set.
+const DLL: usize = 0;
+const DLM: usize = 1;
+const IER: usize = 1;
+const IIR: usize = 2;
+const FCR: usize = 2;
+const LCR: usize = 3;
+const MCR: usize = 4;
+const LSR: usize = 5;
+const MSR: usize = 6;
+/// TI DA8xx power and emulation management.
+const PWREMU_MGMT: usize = 12;
+/// DesignWare UART status.
+const USR: usize = 31;
+/// DesignWare component parameters, zero if not synthesized.
+const CPR: usize = 61;
+
+/// Received data available, and on most UARTs also the RX timeout.
+const IER_RDI: u32 = 1 << 0;
+/// Transmitter holding register empty.
+const IER_THRI: u32 = 1 << 1;
+/// Receiver line status.
+const IER_RLSI: u32 = 1 << 2;
+/// Modem status.
+const IER_MSI: u32 = 1 << 3;
+/// XScale receiver timeout interrupt enable.
+const IER_RTOIE: u32 = 1 << 4;
+/// XScale UART unit enable.
+const IER_UUE: u32 = 1 << 6;
+
+/// No interrupt pending, active high.
+const IIR_NO_INT: u32 = 1 << 0;
+const IIR_ID: u32 = 0x0f;
+const IIR_MSI: u32 = 0x00;
+const IIR_THRI: u32 = 0x02;
+const IIR_RDI: u32 = 0x04;
+const IIR_RLSI: u32 = 0x06;
+/// DesignWare busy detect.
+const IIR_BUSY: u32 = 0x07;
+/// Character timeout.
+const IIR_RX_TIMEOUT: u32 = 0x0c;
+/// 16750: the 64-byte FIFO is enabled.
+const IIR_FIFO64: u32 = 1 << 5;
+/// Both set on a 16550A with working FIFOs, only bit 7 on the 16550.
+const IIR_FIFO: u32 = 0b11 << 6;
+
+const FCR_ENABLE: u32 = 1 << 0;
+const FCR_CLEAR_RX: u32 = 1 << 1;
+const FCR_CLEAR_TX: u32 = 1 << 2;
+/// 16750: 64-byte FIFO, only writable with DLAB set.
+const FCR_FIFO64: u32 = 1 << 5;
+/// RX trigger level: 1, 4, 8 or 14 bytes on a 16550A.
+const FCR_RX_TRIGGER_SHIFT: u32 = 6;
+const FCR_FIFO: u32 = FCR_ENABLE | FCR_CLEAR_RX | FCR_CLEAR_TX;
+
+const LCR_STOP: u32 = 1 << 2;
+const LCR_PARITY: u32 = 1 << 3;
+const LCR_EPAR: u32 = 1 << 4;
+const LCR_SPAR: u32 = 1 << 5;
+const LCR_DLAB: u32 = 1 << 7;
+
+const MCR_DTR: u32 = 1 << 0;
+const MCR_RTS: u32 = 1 << 1;
+const MCR_OUT2: u32 = 1 << 3;
+/// Auto flow control enable, 16750 and DesignWare.
+const MCR_AFE: u32 = 1 << 5;
+
+const LSR_OE: u32 = 1 << 1;
+const LSR_PE: u32 = 1 << 2;
+const LSR_FE: u32 = 1 << 3;
+const LSR_BI: u32 = 1 << 4;
+/// Transmitter holding register, or with FIFOs the TX FIFO, empty.
+const LSR_THRE: u32 = 1 << 5;
+const LSR_TEMT: u32 = 1 << 6;
+
+const USR_BUSY: u32 = 1 << 0;
+/// As in Linux's `dw8250_check_lcr`.
+const DW_IDLE_RETRIES: usize = 1000;
+
+/// `FIFO_MODE`, the FIFO depth in units of 16 bytes.
+const CPR_FIFO_MODE_SHIFT: u32 = 16;
+const CPR_FIFO_MODE_MASK: u32 = 0xff;
+
+const PWREMU_FREE: u32 = 1 << 0;
+const PWREMU_URRST: u32 = 1 << 13;
+const PWREMU_UTRST: u32 = 1 << 14;
 
 pub struct Ns16550 {}
 
 impl Ns16550 {
     fn sts(uart: UartData) -> u32 {
-        Self::read(uart, 5)
+        Self::read(uart, LSR)
+    }
+
+    /// Wait until LCR may be written: DesignWare ignores LCR writes while busy.
+    ///
+    /// DesignWare also stays busy while received data is unread, so like
+    /// Linux's `dw8250_force_idle` the FIFOs are cleared and RBR read, a
+    /// bounded number of times. This resets the RX trigger level.
+    fn wait_idle(uart: UartData) {
+        while Self::sts(uart) & LSR_TEMT == 0 {}
+
+        if !uart.quirks.contains(Quirks::DW_APB) {
+            return;
+        }
+
+        // FCR is write only, IIR tells whether the FIFOs are on.
+        let fcr = match Self::read(uart, IIR) & IIR_FIFO {
+            0 => FCR_CLEAR_RX | FCR_CLEAR_TX,
+            _ => FCR_FIFO,
+        };
+
+        for _ in 0..DW_IDLE_RETRIES {
+            if Self::read(uart, USR) & USR_BUSY == 0 {
+                return;
+            }
+
+            Self::write(uart, FCR, fcr);
+            Self::read(uart, RBR);
+        }
     }
 
     fn write(uart: UartData, reg: usize, val: u32) {
+        let addr = uart.reg_addr(reg);
         unsafe {
             match uart.io_kind {
                 IoKind::Port => {
                     cfg_if! {
                         if #[cfg(target_arch = "x86_64")] {
-                            x86_64::instructions::port::Port::<u8>::new((uart.base + reg) as _)  .write(val as _);
+                            x86_64::instructions::port::Port::<u8>::new(addr as _).write(val as _);
                         } else {
                             todo!();
                         }
                     }
                 }
+                IoKind::Mmio => {
+                    (addr as *mut u8).write_volatile(val as _);
+                }
                 IoKind::Mmio16 => {
-                    uart.reg::<u16>(reg).write_volatile(val as _);
+                    (addr as *mut u16).write_volatile(val as _);
                 }
-                IoKind::Mmio32 | IoKind::Mmio => {
-                    uart.reg::<u32>(reg).write_volatile(val);
+                IoKind::Mmio32 => {
+                    (addr as *mut u32).write_volatile(val);
                 }
                 IoKind::Mmio32be => {
-                    uart.reg::<u32>(reg).write_volatile(val.to_be());
+                    (addr as *mut u32).write_volatile(val.to_be());
                 }
             }
         }
     }
 
     fn read(uart: UartData, reg: usize) -> u32 {
+        let addr = uart.reg_addr(reg);
         unsafe {
             match uart.io_kind {
                 IoKind::Port => {
                     cfg_if! {
                         if #[cfg(target_arch = "x86_64")] {
-                            x86_64::instructions::port::Port::<u8>::new((uart.base + reg) as _)
-                                .read() as u32
+                            x86_64::instructions::port::Port::<u8>::new(addr as _).read() as u32
                         } else {
                             todo!();
                         }
                     }
                 }
-                IoKind::Mmio16 => uart.reg::<u16>(reg).read_volatile() as _,
-                IoKind::Mmio32 | IoKind::Mmio => uart.reg::<u32>(reg).read_volatile(),
-                IoKind::Mmio32be => {
-                    let reg = uart.reg::<u32>(reg);
-                    let val = reg.read_volatile();
-                    u32::from_be(val)
-                }
+                IoKind::Mmio => (addr as *mut u8).read_volatile() as _,
+                IoKind::Mmio16 => (addr as *mut u16).read_volatile() as _,
+                IoKind::Mmio32 => (addr as *mut u32).read_volatile(),
+                IoKind::Mmio32be => u32::from_be((addr as *mut u32).read_volatile()),
             }
         }
     }
@@ -61,30 +184,200 @@ impl Ns16550 {
 
 impl Console for Ns16550 {
     fn put(uart: UartData, c: u8) -> Result<(), ErrorKind> {
-        Self::write(uart, 0, c as _);
+        Self::write(uart, THR, c as _);
         Ok(())
     }
 
     fn get(uart: UartData) -> Result<u8, ErrorKind> {
-        Ok(Self::read(uart, 0) as _)
+        Ok(Self::read(uart, RBR) as _)
     }
 
     fn set_irq_enable(uart: UartData, enable: bool) {
-        let val = if enable { 1 | 1 << 1 } else { 0 };
-
-        Self::write(uart, 1, val);
+        Self::set_irq_mask(
+            uart,
+            IrqMask {
+                rx: enable,
+                tx: enable,
+                rx_timeout: enable,
+                ..Default::default()
+            },
+        );
     }
 
     fn get_irq_enable(uart: UartData) -> bool {
-        Self::read(uart, 1) != 0
+        Self::read(uart, IER) & !IER_UUE != 0
+    }
+
+    fn set_irq_mask(uart: UartData, mask: IrqMask) {
+        let rtoie = uart.quirks.contains(Quirks::RTOIE);
+        let mut ier = 0;
+
+        // Without a separate enable, the RX timeout comes with RDI.
+        if mask.rx || (mask.rx_timeout && !rtoie) {
+            ier |= IER_RDI;
+        }
+        if mask.rx_timeout && rtoie {
+            ier |= IER_RTOIE;
+        }
+        if mask.tx {
+            ier |= IER_THRI;
+        }
+        if mask.line_status {
+            ier |= IER_RLSI;
+        }
+        if mask.modem_status {
+            ier |= IER_MSI;
+        }
+        if uart.quirks.contains(Quirks::XSCALE_UUE) {
+            ier |= IER_UUE;
+        }
+
+        Self::write(uart, IER, ier);
+    }
+
+    fn get_irq_mask(uart: UartData) -> IrqMask {
+        let ier = Self::read(uart, IER);
+        let rx_timeout = if uart.quirks.contains(Quirks::RTOIE) {
+            IER_RTOIE
+        } else {
+            IER_RDI
+        };
+
+        IrqMask {
+            rx: ier & IER_RDI != 0,
+            tx: ier & IER_THRI != 0,
+            rx_timeout: ier & rx_timeout != 0,
+            line_status: ier & IER_RLSI != 0,
+            modem_status: ier & IER_MSI != 0,
+        }
+    }
+
+    fn set_config(uart: UartData, config: &Config) -> Result<(), ConfigError> {
+        let mut mcr = MCR_DTR | MCR_RTS | MCR_OUT2;
+        if config.flow_control {
+            if !uart.quirks.intersects(Quirks::FIFO64 | Quirks::DW_APB) {
+                return Err(ConfigError::Unsupported);
+            }
+            mcr |= MCR_AFE;
+        }
+
+        if uart.clock == 0 {
+            return Err(ConfigError::UnknownClock);
+        }
+
+        let baud = config.baud as u64 * 16;
+        let div = (uart.clock as u64 + baud / 2)
+            .checked_div(baud)
+            .unwrap_or(0) as u32;
+        if div == 0 || div > 0xffff {
+            return Err(ConfigError::InvalidBaud);
+        }
+
+        let mut lcr = match config.data_bits {
+            DataBits::Five => 0,
+            DataBits::Six => 1,
+            DataBits::Seven => 2,
+            DataBits::Eight => 3,
+        };
+
+        if config.stop_bits == StopBits::Two {
+            lcr |= LCR_STOP;
+        }
+
+        lcr |= match config.parity {
+            Parity::None => 0,
+            Parity::Odd => LCR_PARITY,
+            Parity::Even => LCR_PARITY | LCR_EPAR,
+            Parity::Mark => LCR_PARITY | LCR_SPAR,
+            Parity::Space => LCR_PARITY | LCR_EPAR | LCR_SPAR,
+        };
+
+        Self::wait_idle(uart);
+
+        Self::write(uart, LCR, lcr | LCR_DLAB);
+        Self::write(uart, DLL, div & 0xff);
+        Self::write(uart, DLM, div >> 8);
+        Self::write(uart, LCR, lcr);
+
+        Self::write(uart, MCR, mcr);
+
+        if uart.quirks.contains(Quirks::XSCALE_UUE) {
+            let ier = Self::read(uart, IER);
+            Self::write(uart, IER, ier | IER_UUE);
+        }
+
+        if uart.quirks.contains(Quirks::DA830_PWREMU) {
+            Self::write(uart, PWREMU_MGMT, PWREMU_UTRST | PWREMU_URRST | PWREMU_FREE);
+        }
+
+        Ok(())
+    }
+
+    /// The depth comes from `CPR` on DesignWare, else from probing FCR as
+    /// Linux does: none on the 16450, 16 bytes on the 16550A and 64 on the
+    /// 16750. The FIFOs of the original 16550 are broken and left disabled.
+    ///
+    /// There is only an RX trigger level, `tx_level` is ignored.
+    fn enable_fifo(uart: UartData, fifo: &FifoConfig) -> usize {
+        if uart.quirks.contains(Quirks::NO_FIFO) {
+            Self::write(uart, FCR, 0);
+            return 1;
+        }
+
+        let trigger = match fifo.rx_level {
+            FifoLevel::OneEighth => 0,
+            FifoLevel::OneQuarter => 1,
+            FifoLevel::Half => 2,
+            FifoLevel::ThreeQuarters | FifoLevel::SevenEighths => 3,
+        };
+        let fcr = FCR_FIFO | trigger << FCR_RX_TRIGGER_SHIFT;
+
+        if uart.quirks.contains(Quirks::DW_APB) {
+            let mode = (Self::read(uart, CPR) >> CPR_FIFO_MODE_SHIFT) & CPR_FIFO_MODE_MASK;
+            if mode != 0 {
+                Self::write(uart, FCR, fcr);
+                return mode as usize * 16;
+            }
+        }
+
+        if uart.quirks.contains(Quirks::FIFO64) {
+            let lcr = Self::read(uart, LCR);
+            Self::write(uart, LCR, lcr | LCR_DLAB);
+            Self::write(uart, FCR, fcr | FCR_FIFO64);
+            let iir = Self::read(uart, IIR);
+            Self::write(uart, LCR, lcr);
+
+            if iir & (IIR_FIFO | IIR_FIFO64) == IIR_FIFO | IIR_FIFO64 {
+                return 64;
+            }
+        }
+
+        Self::write(uart, FCR, fcr);
+        if Self::read(uart, IIR) & IIR_FIFO == IIR_FIFO {
+            return 16;
+        }
+
+        Self::write(uart, FCR, 0);
+        1
     }
 
-    fn clean_irq_event(_uart: UartData, _event: IrqEvent) {}
+    /// RX and RX timeout are cleared by reading the data, and TX by reading
+    /// IIR or writing data. The others need a status register read.
+    fn clean_irq_event(uart: UartData, event: IrqEvent) {
+        if event.intersects(IrqEvent::LINE_STATUS) {
+            Self::read(uart, LSR);
+        }
+        if event.contains(IrqEvent::MODEM_STATUS) {
+            Self::read(uart, MSR);
+        }
+        if event.contains(IrqEvent::BUSY_DETECT) && uart.quirks.contains(Quirks::DW_APB) {
+            Self::read(uart, USR);
+        }
+    }
 
+    /// Room for [`UartData::fifo_depth`] bytes.
     fn can_put(uart: UartData) -> bool {
-        // Xmitter empty
-        const LSR_TEMT: u32 = 1 << 6;
-        Self::sts(uart) & LSR_TEMT != 0
+        Self::sts(uart) & LSR_THRE != 0
     }
 
     fn can_get(uart: UartData) -> bool {
@@ -94,17 +387,27 @@ impl Console for Ns16550 {
     }
 
     fn get_irq_event(uart: UartData) -> IrqEvent {
-        let sts = Self::read(uart, 2);
-        let mut event = IrqEvent::default();
-
-        if sts & 1 != 0 {
-            event.rx = true;
-        }
+        let iir = Self::read(uart, IIR);
 
-        if sts & 1 << 1 != 0 {
-            event.tx = true;
+        // Only the highest priority interrupt is reported.
+        match iir & IIR_ID {
+            IIR_BUSY if uart.quirks.contains(Quirks::DW_APB) => IrqEvent::BUSY_DETECT,
+            _ if iir & IIR_NO_INT != 0 => IrqEvent::empty(),
+            IIR_RLSI => {
+                // Reading LSR also clears the errors.
+                let lsr = Self::sts(uart);
+                let mut event = IrqEvent::empty();
+                event.set(IrqEvent::OVERRUN, lsr & LSR_OE != 0);
+                event.set(IrqEvent::PARITY, lsr & LSR_PE != 0);
+                event.set(IrqEvent::FRAMING, lsr & LSR_FE != 0);
+                event.set(IrqEvent::BREAK, lsr & LSR_BI != 0);
+                event
+            }
+            IIR_RDI => IrqEvent::RX,
+            IIR_RX_TIMEOUT => IrqEvent::RX_TIMEOUT,
+            IIR_THRI => IrqEvent::TX,
+            IIR_MSI => IrqEvent::MODEM_STATUS,
+            _ => IrqEvent::empty(),
         }
-
-        event
     }
 }
@@ -0,0 +1,587 @@
+use fdt_parser::Fdt;
+
+use crate::{
+    Console, ConsoleOp, IoKind, Node, ProbeError, Quirks, UartOp, aux_mini::AuxMini,
+    ns16550::Ns16550, pl011::Pl011,
+};
+
+/// One row of the device tree match table.
+#[derive(Clone, Copy)]
+pub struct OfMatch {
+    /// Compared exactly against each entry of the node's `compatible`.
+    pub compatible: &'static str,
+    /// Name of the driver handling this compatible.
+    pub driver: &'static str,
+    /// Access kind used when the node has no `reg-io-width`.
+    pub io_kind: IoKind,
+    /// Register shift used when the node has no `reg-shift`.
+    pub reg_shift: u8,
+    pub quirks: Quirks,
+    pub(crate) op: fn() -> UartOp,
+}
+
+impl OfMatch {
+    /// Describe a compatible handled by driver `C`.
+    pub const fn new<C: Console>(
+        compatible: &'static str,
+        driver: &'static str,
+        io_kind: IoKind,
+        reg_shift: u8,
+        quirks: Quirks,
+    ) -> Self {
+        Self {
+            compatible,
+            driver,
+            io_kind,
+            reg_shift,
+            quirks,
+            op: C::to_op,
+        }
+    }
+}
+
+const NONE: Quirks = Quirks::empty();
+const XSCALE: Quirks = Quirks::XSCALE_UUE.union(Quirks::RTOIE);
+
+/// Every compatible this crate knows how to drive.
+pub static OF_MATCH_TABLE: &[OfMatch] = &[
+    OfMatch::new::<AuxMini>("brcm,bcm2835-aux-uart", "aux_mini", IoKind::Mmio32, 0, NONE),
+    OfMatch::new::<Pl011>("arm,pl011", "pl011", IoKind::Mmio32, 0, NONE),
+    OfMatch::new::<Pl011>("arm,primecell", "pl011", IoKind::Mmio32, 0, NONE),
+    OfMatch::new::<Ns16550>(
+        "snps,dw-apb-uart",
+        "ns16550",
+        IoKind::Mmio32,
+        2,
+        Quirks::DW_APB,
+    ),
+    OfMatch::new::<Ns16550>("intel,xscale-uart", "ns16550", IoKind::Mmio32, 2, XSCALE),
+    OfMatch::new::<Ns16550>("mrvl,mmp-uart", "ns16550", IoKind::Mmio32, 2, XSCALE),
+    OfMatch::new::<Ns16550>("mrvl,pxa-uart", "ns16550", IoKind::Mmio32, 2, XSCALE),
+    OfMatch::new::<Ns16550>(
+        "nvidia,tegra20-uart",
+        "ns16550",
+        IoKind::Mmio,
+        2,
+        Quirks::RTOIE,
+    ),
+    OfMatch::new::<Ns16550>(
+        "ti,da830-uart",
+        "ns16550",
+        IoKind::Mmio32,
+        2,
+        Quirks::DA830_PWREMU,
+    ),
+    OfMatch::new::<Ns16550>("mediatek,mt6577-uart", "ns16550", IoKind::Mmio32, 2, NONE),
+    OfMatch::new::<Ns16550>("ns8250", "ns16550", IoKind::Mmio, 0, Quirks::NO_FIFO),
+    OfMatch::new::<Ns16550>("ns16450", "ns16550", IoKind::Mmio, 0, Quirks::NO_FIFO),
+    OfMatch::new::<Ns16550>("ns16550", "ns16550", IoKind::Mmio, 0, Quirks::NO_FIFO),
+    OfMatch::new::<Ns16550>("ns16550a", "ns16550", IoKind::Mmio, 0, NONE),
+    OfMatch::new::<Ns16550>("ns16750", "ns16550", IoKind::Mmio, 0, Quirks::FIFO64),
+    OfMatch::new::<Ns16550>("ns16850", "ns16550", IoKind::Mmio, 0, NONE),
+    OfMatch::new::<Ns16550>("altr,16550-FIFO32", "ns16550", IoKind::Mmio, 0, NONE),
+    OfMatch::new::<Ns16550>("altr,16550-FIFO64", "ns16550", IoKind::Mmio, 0, NONE),
+    OfMatch::new::<Ns16550>("altr,16550-FIFO128", "ns16550", IoKind::Mmio, 0, NONE),
+];
+
+/// The [`OF_MATCH_TABLE`] entry for `compatible`.
+pub(crate) fn of_find(compatible: &str) -> Option<&'static OfMatch> {
+    OF_MATCH_TABLE.iter().find(|m| m.compatible == compatible)
+}
+
+/// Find the table entry for `node`.
+///
+/// The node's compatibles are tried in order, so the most specific one wins.
+/// `None` means none of them is in [`OF_MATCH_TABLE`].
+pub fn of_match(node: &Node<'_>) -> Option<&'static OfMatch> {
+    node.compatibles().find_map(of_find)
+}
+
+/// Like [`of_match`], but for each compatible `drivers` are searched before
+/// [`OF_MATCH_TABLE`], so they can add new compatibles or override built-in ones.
+pub fn of_match_with<'a>(node: &Node<'_>, drivers: &'a [OfMatch]) -> Option<&'a OfMatch> {
+    node.compatibles().find_map(|c| {
+        drivers
+            .iter()
+            .chain(OF_MATCH_TABLE)
+            .find(|m| m.compatible == c)
+    })
+}
+
+/// The `/aliases` name of a UART node, e.g. `serial2`.
+pub fn serial_alias<'a>(node: &Node<'a>) -> Option<&'a str> {
+    let fdt = node.fdt();
+    let aliases = fdt.find_nodes("/aliases").next()?;
+
+    aliases
+        .propertys()
+        .filter(|prop| prop.name.starts_with("serial"))
+        .find(|prop| {
+            let path = prop.str();
+            path.starts_with('/')
+                && fdt
+                    .find_nodes(path)
+                    .next()
+                    .is_some_and(|n| same_node(&n, node))
+        })
+        .map(|prop| prop.name)
+}
+
+/// Whether `a` and `b` are the same node of one blob.
+///
+/// Node names point into the blob, so their addresses identify the node,
+/// while the same name may appear under different buses.
+fn same_node(a: &Node<'_>, b: &Node<'_>) -> bool {
+    core::ptr::eq(a.name, b.name)
+}
+
+/// Read `reg-io-width`, `big-endian` and `reg-shift` from the node, falling
+/// back to the defaults of the matched compatible.
+pub(crate) fn fdt_io_config(node: &Node<'_>, io_kind: IoKind, reg_shift: u8) -> (IoKind, u8) {
+    let big_endian = node.find_property("big-endian").is_some();
+
+    let io_kind = node
+        .find_property("reg-io-width")
+        .and_then(|p| IoKind::from_reg_io_width(p.u32(), big_endian))
+        .unwrap_or(match io_kind {
+            IoKind::Mmio32 if big_endian => IoKind::Mmio32be,
+            other => other,
+        });
+
+    let reg_shift = node
+        .find_property("reg-shift")
+        .map(|p| p.u32() as u8)
+        .unwrap_or(reg_shift);
+
+    (io_kind, reg_shift)
+}
+
+/// Node at `path`, which may be an alias like `serial0` as in `stdout-path`.
+pub(crate) fn fdt_find_path<'a>(fdt: &Fdt<'a>, path: &'a str) -> Result<Node<'a>, ProbeError<'a>> {
+    let path = if path.starts_with('/') {
+        path
+    } else {
+        fdt.find_aliase(path).ok_or(ProbeError::NoAlias(path))?
+    };
+
+    // fdt-parser looks up a relative path as an alias, and panics if there
+    // is none.
+    path.starts_with('/')
+        .then(|| fdt.find_nodes(path).next())
+        .flatten()
+        .ok_or(ProbeError::NoNode(path))
+}
+
+/// The first interrupt of a UART node, as its interrupt controller encodes it.
+///
+/// For an Arm GIC the cells are `<type number flags>`, where type `0` is an
+/// SPI and flags `4` is level high. A RISC-V PLIC has one cell, the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IrqSpec {
+    /// Phandle of the interrupt controller.
+    pub parent: u32,
+    len: usize,
+    cells: [u32; IrqSpec::MAX_CELLS],
+}
+
+impl IrqSpec {
+    const MAX_CELLS: usize = 4;
+
+    /// The specifier, `#interrupt-cells` of the controller long.
+    pub fn cells(&self) -> &[u32] {
+        &self.cells[..self.len]
+    }
+}
+
+/// First entry of `interrupts-extended`, or else of `interrupts` with the
+/// inherited `interrupt-parent`.
+pub(crate) fn fdt_irq(node: &Node<'_>) -> Option<IrqSpec> {
+    let (parent, mut cells) = match node.find_property("interrupts-extended") {
+        Some(prop) => {
+            let mut cells = prop.u32_list();
+            (cells.next()?, cells)
+        }
+        None => {
+            let cells = node.find_property("interrupts")?.u32_list();
+            let ancestors = Ancestors::of(node);
+            let parent = core::iter::once(node)
+                .chain(ancestors.parents(node.level)?)
+                .find_map(|n| n.find_property("interrupt-parent"))?
+                .u32();
+            (parent, cells)
+        }
+    };
+
+    let controller = node.fdt().get_node_by_phandle(parent.into())?;
+    let len = controller.find_property("#interrupt-cells")?.u32() as usize;
+
+    let mut spec = IrqSpec {
+        parent,
+        len,
+        cells: [0; IrqSpec::MAX_CELLS],
+    };
+    for cell in spec.cells.get_mut(..len)? {
+        *cell = cells.next()?;
+    }
+    Some(spec)
+}
+
+/// CPU physical address of the first `reg` entry of `node`, translated
+/// through the `ranges` of every bus above it.
+pub(crate) fn fdt_reg_address<'a>(node: &Node<'a>) -> Result<u64, ProbeError<'a>> {
+    reg_address(node, &Ancestors::of(node))
+}
+
+/// [`fdt_reg_address`], with the ancestors of `node` already known.
+fn reg_address<'a>(node: &Node<'a>, ancestors: &Ancestors<'a>) -> Result<u64, ProbeError<'a>> {
+    let reg = node.find_property("reg").ok_or(ProbeError::NoReg)?;
+    let mut parents = ancestors
+        .parents(node.level)
+        .ok_or(ProbeError::Untranslatable)?;
+    let mut bus = parents.next().ok_or(ProbeError::NoReg)?;
+    let mut addr = read_cells(reg.raw_value(), address_cells(bus)).ok_or(ProbeError::NoReg)?;
+
+    for parent in parents {
+        addr = fdt_translate(bus, parent, addr).ok_or(ProbeError::Untranslatable)?;
+        bus = parent;
+    }
+
+    Ok(addr)
+}
+
+/// Map `addr` on `bus` into the address space of its `parent`.
+///
+/// A bus without `ranges` is not memory mapped, while an empty `ranges` is
+/// the identity.
+fn fdt_translate(bus: &Node<'_>, parent: &Node<'_>, addr: u64) -> Option<u64> {
+    let ranges = bus.find_property("ranges")?.raw_value();
+    if ranges.is_empty() {
+        return Some(addr);
+    }
+
+    let child_cells = address_cells(bus);
+    let parent_cells = address_cells(parent);
+    let size_cells = size_cells(bus);
+    let width = 4 * (child_cells + parent_cells + size_cells);
+    if width == 0 {
+        return None;
+    }
+
+    ranges.chunks_exact(width).find_map(|range| {
+        let (child, rest) = range.split_at(4 * child_cells);
+        let (parent, size) = rest.split_at(4 * parent_cells);
+        let offset = addr.checked_sub(read_cells(child, child_cells)?)?;
+        let parent = read_cells(parent, parent_cells)?;
+        if offset < read_cells(size, size_cells)? {
+            parent.checked_add(offset)
+        } else {
+            None
+        }
+    })
+}
+
+/// The nodes above the one being visited, kept up to date while walking the
+/// tree once in order, instead of searching for each parent from the root.
+struct Ancestors<'a> {
+    /// The last node seen at each level, the root at level 0.
+    levels: [Option<Node<'a>>; Ancestors::MAX_DEPTH],
+}
+
+impl<'a> Ancestors<'a> {
+    const MAX_DEPTH: usize = 16;
+
+    fn new() -> Self {
+        Self {
+            levels: [const { None }; Self::MAX_DEPTH],
+        }
+    }
+
+    /// The ancestors of `node`, from a walk of the tree up to it.
+    fn of(node: &Node<'a>) -> Self {
+        let mut ancestors = Self::new();
+        for n in node.fdt().all_nodes() {
+            if same_node(&n, node) {
+                break;
+            }
+            ancestors.visit(n);
+        }
+        ancestors
+    }
+
+    /// Record `node`, the next one in tree order.
+    fn visit(&mut self, node: Node<'a>) {
+        if let Some(level) = self.levels.get_mut(node.level) {
+            *level = Some(node);
+        }
+    }
+
+    /// The ancestors of a node at `level`, nearest first, `None` if it is
+    /// nested too deep to track.
+    fn parents(&self, level: usize) -> Option<impl Iterator<Item = &Node<'a>>> {
+        Some(self.levels.get(..level)?.iter().rev().flatten())
+    }
+}
+
+fn address_cells(node: &Node<'_>) -> usize {
+    node.find_property("#address-cells")
+        .map_or(2, |p| p.u32() as usize)
+}
+
+fn size_cells(node: &Node<'_>) -> usize {
+    node.find_property("#size-cells")
+        .map_or(1, |p| p.u32() as usize)
+}
+
+/// Big-endian number of `cells` 32-bit cells at the start of `bytes`.
+fn read_cells(bytes: &[u8], cells: usize) -> Option<u64> {
+    let (cells, _) = bytes.get(..4 * cells)?.as_chunks::<4>();
+    Some(cells.iter().fold(0u64, |acc, cell| {
+        acc << 32 | u32::from_be_bytes(*cell) as u64
+    }))
+}
+
+/// First node with a driver, in `drivers` or [`OF_MATCH_TABLE`], whose `reg`
+/// translates to the CPU address `addr`, see [`fdt_reg_address`].
+pub(crate) fn fdt_find_node_by_addr<'a>(
+    fdt: &Fdt<'a>,
+    addr: u64,
+    drivers: &[OfMatch],
+) -> Option<Node<'a>> {
+    let mut ancestors = Ancestors::new();
+    for node in fdt.all_nodes() {
+        // Matching is cheaper than translating, and rules out most nodes.
+        if of_match_with(&node, drivers).is_some()
+            && reg_address(&node, &ancestors).is_ok_and(|reg| reg == addr)
+        {
+            return Some(node);
+        }
+        ancestors.visit(node);
+    }
+    None
+}
+
+/// Reference clock of `node`.
+///
+/// Taken from `clock-frequency`, or else from the `baudclk`/`uartclk` (or
+/// first) entry of `clocks` when it resolves to a `fixed-clock`, possibly
+/// through `fixed-factor-clock`s.
+pub(crate) fn fdt_input_clock(node: &Node<'_>) -> Option<u32> {
+    if let Some(freq) = node.clock_frequency() {
+        return Some(freq);
+    }
+
+    let index = node
+        .find_property("clock-names")
+        .and_then(|p| {
+            p.str_list()
+                .position(|name| name == "baudclk" || name == "uartclk")
+        })
+        .unwrap_or(0);
+
+    fdt_clock_rate(&fdt_clock(node, index)?, 4)
+}
+
+/// Provider node of the `index`th entry of `clocks`.
+fn fdt_clock<'a>(node: &Node<'a>, index: usize) -> Option<Node<'a>> {
+    let fdt = node.fdt();
+    let mut cells = node.find_property("clocks")?.u32_list();
+
+    for i in 0.. {
+        let provider = fdt.get_node_by_phandle(cells.next()?.into())?;
+        if i == index {
+            return Some(provider);
+        }
+
+        let clock_cells = provider.find_property("#clock-cells")?.u32();
+        for _ in 0..clock_cells {
+            cells.next()?;
+        }
+    }
+
+    None
+}
+
+fn fdt_clock_rate(clock: &Node<'_>, depth: usize) -> Option<u32> {
+    if clock.compatibles().any(|c| c == "fixed-clock") {
+        return clock.clock_frequency();
+    }
+
+    if depth > 0 && clock.compatibles().any(|c| c == "fixed-factor-clock") {
+        let parent = fdt_clock_rate(&fdt_clock(clock, 0)?, depth - 1)? as u64;
+        let mult = clock.find_property("clock-mult")?.u32() as u64;
+        let div = clock.find_property("clock-div")?.u32() as u64;
+        return (parent * mult).checked_div(div).map(|rate| rate as u32);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorKind, IrqEvent, UartData};
+
+    struct Dummy;
+
+    impl Console for Dummy {
+        fn can_put(_uart: UartData) -> bool {
+            true
+        }
+        fn put(_uart: UartData, _c: u8) -> Result<(), ErrorKind> {
+            Ok(())
+        }
+        fn can_get(_uart: UartData) -> bool {
+            false
+        }
+        fn get(_uart: UartData) -> Result<u8, ErrorKind> {
+            Err(ErrorKind::Other)
+        }
+        fn set_irq_enable(_uart: UartData, _enable: bool) {}
+        fn get_irq_enable(_uart: UartData) -> bool {
+            false
+        }
+        fn get_irq_event(_uart: UartData) -> IrqEvent {
+            IrqEvent::default()
+        }
+        fn clean_irq_event(_uart: UartData, _event: IrqEvent) {}
+    }
+
+    static DRIVERS: &[OfMatch] = &[OfMatch::new::<Dummy>(
+        "rockchip,rk3568-uart",
+        "dummy",
+        IoKind::Mmio32,
+        2,
+        Quirks::empty(),
+    )];
+
+    #[test]
+    fn test_fdt_input_clock() {
+        let fdt = include_bytes!("../../dtb/test-board.dtb");
+        let fdt = Fdt::from_bytes(fdt).unwrap();
+        let clock = |path| fdt_input_clock(&fdt.find_nodes(path).next().unwrap());
+        assert_eq!(clock("/soc/serial@10000000"), Some(3_686_400));
+        assert_eq!(clock("/soc/serial@10001000"), Some(24_000_000));
+        assert_eq!(clock("/soc/serial@10002000"), Some(12_000_000));
+
+        // Clocked by the CRU, which we can't compute.
+        let fdt = include_bytes!("../../dtb/rk3568-firefly-roc-pc-se.dtb");
+        let fdt = Fdt::from_bytes(fdt).unwrap();
+        let node = fdt.find_nodes("/serial@fe660000").next().unwrap();
+        assert_eq!(fdt_input_clock(&node), None);
+    }
+
+    #[test]
+    fn test_of_find() {
+        let cases = [
+            ("ns8250", IoKind::Mmio, 0, Quirks::NO_FIFO),
+            ("ns16450", IoKind::Mmio, 0, Quirks::NO_FIFO),
+            ("ns16550", IoKind::Mmio, 0, Quirks::NO_FIFO),
+            ("ns16550a", IoKind::Mmio, 0, Quirks::empty()),
+            ("ns16750", IoKind::Mmio, 0, Quirks::FIFO64),
+            ("snps,dw-apb-uart", IoKind::Mmio32, 2, Quirks::DW_APB),
+            ("intel,xscale-uart", IoKind::Mmio32, 2, XSCALE),
+            ("mrvl,mmp-uart", IoKind::Mmio32, 2, XSCALE),
+            ("mrvl,pxa-uart", IoKind::Mmio32, 2, XSCALE),
+            ("nvidia,tegra20-uart", IoKind::Mmio, 2, Quirks::RTOIE),
+            ("ti,da830-uart", IoKind::Mmio32, 2, Quirks::DA830_PWREMU),
+        ];
+
+        for (compatible, io_kind, reg_shift, quirks) in cases {
+            let m = of_find(compatible).unwrap();
+            assert_eq!(m.driver, "ns16550", "{compatible}");
+            assert_eq!(m.io_kind, io_kind, "{compatible}");
+            assert_eq!(m.reg_shift, reg_shift, "{compatible}");
+            assert_eq!(m.quirks, quirks, "{compatible}");
+        }
+
+        assert_eq!(XSCALE, Quirks::XSCALE_UUE | Quirks::RTOIE);
+        assert!(of_find("ns16550a-bogus").is_none());
+    }
+
+    #[test]
+    fn test_of_match_with() {
+        let fdt = include_bytes!("../../dtb/rk3568-firefly-roc-pc-se.dtb");
+        let fdt = Fdt::from_bytes(fdt).unwrap();
+        let node = fdt.find_nodes("/serial@fe660000").next().unwrap();
+        assert_eq!(of_match_with(&node, DRIVERS).unwrap().driver, "dummy");
+        assert_eq!(of_match_with(&node, &[]).unwrap().driver, "ns16550");
+    }
+
+    #[test]
+    fn test_fdt_reg_address() {
+        let fdt = include_bytes!("../../dtb/test-board.dtb");
+        let fdt = Fdt::from_bytes(fdt).unwrap();
+        let addr = |path| fdt_reg_address(&fdt.find_nodes(path).next().unwrap());
+        assert_eq!(addr("/soc/serial@10000000"), Ok(0x1000_0000));
+        // Through both the apb and the bridge ranges.
+        assert_eq!(addr("/soc/bridge/apb/serial@3000"), Ok(0x4010_3000));
+        assert_eq!(addr("/soc"), Err(ProbeError::NoReg));
+    }
+
+    #[test]
+    fn test_fdt_reg_address_malformed() {
+        let fdt = include_bytes!("../../dtb/malformed.dtb");
+        let fdt = Fdt::from_bytes(fdt).unwrap();
+        let addr = |path| fdt_reg_address(&fdt.find_nodes(path).next().unwrap());
+        assert_eq!(
+            addr("/overflow/serial@2000"),
+            Err(ProbeError::Untranslatable)
+        );
+        assert_eq!(addr("/zero/bus/serial"), Err(ProbeError::Untranslatable));
+        assert!(fdt_find_node_by_addr(&fdt, 0x2000, &[]).is_none());
+    }
+
+    #[test]
+    fn test_fdt_find_node_by_addr() {
+        let fdt = include_bytes!("../../dtb/test-board.dtb");
+        let fdt = Fdt::from_bytes(fdt).unwrap();
+        let name = |addr| fdt_find_node_by_addr(&fdt, addr, &[]).map(|node| node.name);
+        assert_eq!(name(0x1000_1000), Some("serial@10001000"));
+        assert_eq!(name(0x4010_3000), Some("serial@3000"));
+        // Untranslated, on the apb bus.
+        assert_eq!(name(0x3000), None);
+        // Not a UART.
+        assert_eq!(name(0x800_0000), None);
+    }
+
+    #[test]
+    fn test_fdt_irq() {
+        let fdt = include_bytes!("../../dtb/test-board.dtb");
+        let fdt = Fdt::from_bytes(fdt).unwrap();
+        let irq = |path| fdt_irq(&fdt.find_nodes(path).next().unwrap());
+
+        let gic = irq("/soc/serial@10000000").unwrap();
+        assert_eq!(gic.parent, 3);
+        assert_eq!(gic.cells(), [0, 33, 4]);
+
+        let plic = irq("/soc/serial@10001000").unwrap();
+        assert_eq!(plic.parent, 4);
+        assert_eq!(plic.cells(), [10]);
+
+        assert_eq!(irq("/soc/serial@10002000"), None);
+
+        let fdt = include_bytes!("../../dtb/rk3568-firefly-roc-pc-se.dtb");
+        let fdt = Fdt::from_bytes(fdt).unwrap();
+        let node = fdt.find_nodes("/serial@fe660000").next().unwrap();
+        assert_eq!(fdt_irq(&node).unwrap().cells(), [0, 118, 4]);
+    }
+
+    #[test]
+    fn test_fdt_io_config() {
+        let fdt = include_bytes!("../../dtb/rk3568-firefly-roc-pc-se.dtb");
+        let fdt = Fdt::from_bytes(fdt).unwrap();
+        let node = fdt.find_nodes("/serial@fe660000").next().unwrap();
+        assert_eq!(fdt_io_config(&node, IoKind::Mmio, 0), (IoKind::Mmio32, 2));
+
+        let fdt = include_bytes!("../../dtb/test-board.dtb");
+        let fdt = Fdt::from_bytes(fdt).unwrap();
+        let config = |path| {
+            let node = fdt.find_nodes(path).next().unwrap();
+            let m = of_match(&node).unwrap();
+            fdt_io_config(&node, m.io_kind, m.reg_shift)
+        };
+        // The ns16550a defaults, then overridden by the node.
+        assert_eq!(config("/soc/serial@10000000"), (IoKind::Mmio, 0));
+        assert_eq!(config("/soc/serial@10003000"), (IoKind::Mmio32, 2));
+    }
+}
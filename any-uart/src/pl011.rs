@@ -1,6 +1,9 @@
 use bitflags::bitflags;
 
-use crate::{Console, ErrorKind, IrqEvent, UartData};
+use crate::{
+    Config, ConfigError, Console, ErrorKind, FifoConfig, FifoLevel, IrqEvent, IrqMask, Parity,
+    StopBits, UartData,
+};
 
 bitflags! {
     struct Interrupts: u32 {
@@ -26,21 +29,188 @@ bitflags! {
         const CTSMI = 1 << 1;
         /// nUARTRI modem interrupt.
         const RIMI = 1 << 0;
+
+        /// Overrun, break, parity and framing errors.
+        const ERRORS = 0b1111 << 7;
+        /// All modem status interrupts.
+        const MODEM = 0b1111;
+    }
+}
+
+impl From<Interrupts> for IrqEvent {
+    fn from(irqs: Interrupts) -> Self {
+        let mut event = IrqEvent::empty();
+        event.set(IrqEvent::RX, irqs.contains(Interrupts::RXI));
+        event.set(IrqEvent::TX, irqs.contains(Interrupts::TXI));
+        event.set(IrqEvent::RX_TIMEOUT, irqs.contains(Interrupts::RTI));
+        event.set(IrqEvent::OVERRUN, irqs.contains(Interrupts::OEI));
+        event.set(IrqEvent::PARITY, irqs.contains(Interrupts::PEI));
+        event.set(IrqEvent::FRAMING, irqs.contains(Interrupts::FEI));
+        event.set(IrqEvent::BREAK, irqs.contains(Interrupts::BEI));
+        event.set(IrqEvent::MODEM_STATUS, irqs.intersects(Interrupts::MODEM));
+        event
+    }
+}
+
+impl From<IrqEvent> for Interrupts {
+    fn from(event: IrqEvent) -> Self {
+        let mut irqs = Interrupts::empty();
+        irqs.set(Interrupts::RXI, event.contains(IrqEvent::RX));
+        irqs.set(Interrupts::TXI, event.contains(IrqEvent::TX));
+        irqs.set(Interrupts::RTI, event.contains(IrqEvent::RX_TIMEOUT));
+        irqs.set(Interrupts::OEI, event.contains(IrqEvent::OVERRUN));
+        irqs.set(Interrupts::PEI, event.contains(IrqEvent::PARITY));
+        irqs.set(Interrupts::FEI, event.contains(IrqEvent::FRAMING));
+        irqs.set(Interrupts::BEI, event.contains(IrqEvent::BREAK));
+        irqs.set(Interrupts::MODEM, event.contains(IrqEvent::MODEM_STATUS));
+        irqs
+    }
+}
+
+impl From<IrqMask> for Interrupts {
+    fn from(mask: IrqMask) -> Self {
+        let mut irqs = Interrupts::empty();
+        irqs.set(Interrupts::RXI, mask.rx);
+        irqs.set(Interrupts::TXI, mask.tx);
+        irqs.set(Interrupts::RTI, mask.rx_timeout);
+        irqs.set(Interrupts::ERRORS, mask.line_status);
+        irqs.set(Interrupts::MODEM, mask.modem_status);
+        irqs
+    }
+}
+
+bitflags! {
+    struct LineControl: u32 {
+        /// Stick parity select.
+        const SPS = 1 << 7;
+        /// Word length, 5 to 8 bits.
+        const WLEN = 0b11 << 5;
+        /// Enable FIFOs.
+        const FEN = 1 << 4;
+        /// Two stop bits select.
+        const STP2 = 1 << 3;
+        /// Even parity select.
+        const EPS = 1 << 2;
+        /// Parity enable.
+        const PEN = 1 << 1;
+        /// Send break.
+        const BRK = 1 << 0;
     }
 }
 
+bitflags! {
+    #[derive(Clone, Copy)]
+    struct Control: u32 {
+        /// CTS hardware flow control enable.
+        const CTSEN = 1 << 15;
+        /// RTS hardware flow control enable.
+        const RTSEN = 1 << 14;
+        /// Request to send.
+        const RTS = 1 << 11;
+        /// Receive enable.
+        const RXE = 1 << 9;
+        /// Transmit enable.
+        const TXE = 1 << 8;
+        /// UART enable.
+        const UARTEN = 1 << 0;
+    }
+}
+
+const FR: usize = 0x018 / 4;
+const IBRD: usize = 0x024 / 4;
+const FBRD: usize = 0x028 / 4;
+const LCR_H: usize = 0x02C / 4;
+const CR: usize = 0x030 / 4;
+const IFLS: usize = 0x034 / 4;
 const IMSC: usize = 0x038 / 4;
 const RIS: usize = 0x03C / 4;
 const MIS: usize = 0x040 / 4;
 const ICR: usize = 0x044 / 4;
 
+/// UART busy transmitting.
+const FR_BUSY: u32 = 1 << 3;
+
+const IFLS_RX_SHIFT: u32 = 3;
+
 pub struct Pl011 {}
 
+impl Pl011 {
+    /// `IBRD`, `FBRD` and `LCR_H` without `FEN`.
+    fn line(uart: UartData, config: &Config) -> Result<(u32, u32, LineControl), ConfigError> {
+        if uart.clock == 0 {
+            return Err(ConfigError::UnknownClock);
+        }
+
+        // Divisor in 1/64 units: clock / (16 * baud) * 64.
+        let baud = config.baud as u64;
+        let div = (uart.clock as u64 * 4 + baud / 2)
+            .checked_div(baud)
+            .unwrap_or(0);
+        let ibrd = div >> 6;
+        let fbrd = div & 0x3f;
+        if ibrd == 0 || ibrd > 0xffff {
+            return Err(ConfigError::InvalidBaud);
+        }
+
+        let mut lcr = LineControl::from_bits_retain(((config.data_bits.bits() - 5) as u32) << 5);
+
+        if config.stop_bits == StopBits::Two {
+            lcr |= LineControl::STP2;
+        }
+
+        lcr |= match config.parity {
+            Parity::None => LineControl::empty(),
+            Parity::Odd => LineControl::PEN,
+            Parity::Even => LineControl::PEN | LineControl::EPS,
+            Parity::Mark => LineControl::PEN | LineControl::SPS,
+            Parity::Space => LineControl::PEN | LineControl::EPS | LineControl::SPS,
+        };
+
+        Ok((ibrd as _, fbrd as _, lcr))
+    }
+
+    /// Let the transmitter drain and clear `UARTEN`, as required before
+    /// writing `LCR_H`. Returns the old `CR`.
+    ///
+    /// `BUSY` stays set while the TX FIFO holds data, even with the UART
+    /// disabled, so it is only polled while the transmitter runs. Otherwise
+    /// the FIFOs are flushed by clearing `FEN`.
+    fn disable(uart: UartData) -> Control {
+        unsafe {
+            let cr = uart.reg::<u32>(CR);
+            let old_cr = Control::from_bits_retain(cr.read_volatile());
+
+            if old_cr.contains(Control::UARTEN | Control::TXE) {
+                while uart.reg::<u32>(FR).read_volatile() & FR_BUSY != 0 {}
+            } else {
+                let lcr_h = uart.reg::<u32>(LCR_H);
+                lcr_h.write_volatile(lcr_h.read_volatile() & !LineControl::FEN.bits());
+            }
+
+            cr.write_volatile((old_cr - Control::UARTEN).bits());
+
+            old_cr
+        }
+    }
+
+    fn ifls(fifo: &FifoConfig) -> u32 {
+        let level = |level| match level {
+            FifoLevel::OneEighth => 0,
+            FifoLevel::OneQuarter => 1,
+            FifoLevel::Half => 2,
+            FifoLevel::ThreeQuarters => 3,
+            FifoLevel::SevenEighths => 4,
+        };
+        level(fifo.rx_level) << IFLS_RX_SHIFT | level(fifo.tx_level)
+    }
+}
+
 impl Console for Pl011 {
     fn put(uart: UartData, byte: u8) -> Result<(), ErrorKind> {
+        // Word access, as SBSA generic UARTs only decode 32-bit accesses
+        // and every PL011 register may be accessed as a word.
         unsafe {
-            let put = uart.reg_u8(0);
-            put.write_volatile(byte);
+            uart.reg::<u32>(0).write_volatile(byte as _);
             Ok(())
         }
     }
@@ -77,49 +247,121 @@ impl Console for Pl011 {
         }
     }
 
-    fn clean_irq_event(uart: UartData, event: IrqEvent) {
-        let mut irqs = Interrupts::empty();
-        if event.rx {
-            irqs |= Interrupts::RXI
+    fn set_irq_mask(uart: UartData, mask: IrqMask) {
+        unsafe {
+            uart.reg::<u32>(IMSC)
+                .write_volatile(Interrupts::from(mask).bits());
         }
+    }
 
-        if event.tx {
-            irqs |= Interrupts::TXI
+    fn get_irq_mask(uart: UartData) -> IrqMask {
+        let imsc = Interrupts::from_bits_truncate(unsafe { uart.reg::<u32>(IMSC).read_volatile() });
+        IrqMask {
+            rx: imsc.contains(Interrupts::RXI),
+            tx: imsc.contains(Interrupts::TXI),
+            rx_timeout: imsc.contains(Interrupts::RTI),
+            line_status: imsc.intersects(Interrupts::ERRORS),
+            modem_status: imsc.intersects(Interrupts::MODEM),
         }
+    }
 
+    fn clean_irq_event(uart: UartData, event: IrqEvent) {
         unsafe {
             let icr = uart.reg::<u32>(ICR);
-            icr.write_volatile(irqs.bits());
+            icr.write_volatile(Interrupts::from(event).bits());
+        }
+    }
+
+    fn set_config(uart: UartData, config: &Config) -> Result<(), ConfigError> {
+        let (ibrd, fbrd, lcr) = Self::line(uart, config)?;
+
+        let lcr_h = uart.reg::<u32>(LCR_H);
+        let fen =
+            LineControl::from_bits_retain(unsafe { lcr_h.read_volatile() }) & LineControl::FEN;
+        let old_cr = Self::disable(uart);
+
+        unsafe {
+            uart.reg::<u32>(IBRD).write_volatile(ibrd);
+            uart.reg::<u32>(FBRD).write_volatile(fbrd);
+            // Writing LCR_H latches IBRD and FBRD.
+            lcr_h.write_volatile((lcr | fen).bits());
+
+            let mut new_cr = old_cr - Control::RTSEN - Control::CTSEN;
+            new_cr |= Control::UARTEN | Control::TXE | Control::RXE;
+            if config.flow_control {
+                new_cr |= Control::RTSEN | Control::CTSEN;
+            }
+            uart.reg::<u32>(CR).write_volatile(new_cr.bits());
+        }
+
+        Ok(())
+    }
+
+    /// `TXFF` only tells there is room for one more byte, so this returns
+    /// `1` whatever the depth.
+    fn enable_fifo(uart: UartData, fifo: &FifoConfig) -> usize {
+        let old_cr = Self::disable(uart);
+
+        unsafe {
+            uart.reg::<u32>(IFLS).write_volatile(Self::ifls(fifo));
+
+            let lcr_h = uart.reg::<u32>(LCR_H);
+            lcr_h.write_volatile(lcr_h.read_volatile() | LineControl::FEN.bits());
+
+            uart.reg::<u32>(CR).write_volatile(old_cr.bits());
         }
+
+        1
+    }
+
+    /// Unlike [`Console::set_config`], nothing is kept from the old state:
+    /// interrupts are masked and cleared, and the FIFOs flushed.
+    fn init(uart: UartData, config: &Config, fifo: &FifoConfig) -> Result<usize, ConfigError> {
+        let (ibrd, fbrd, lcr) = Self::line(uart, config)?;
+
+        Self::disable(uart);
+
+        unsafe {
+            uart.reg::<u32>(CR).write_volatile(0);
+            // Clearing FEN flushes the FIFOs.
+            uart.reg::<u32>(LCR_H).write_volatile(0);
+
+            uart.reg::<u32>(IMSC).write_volatile(0);
+            uart.reg::<u32>(ICR)
+                .write_volatile(Interrupts::all().bits());
+
+            uart.reg::<u32>(IBRD).write_volatile(ibrd);
+            uart.reg::<u32>(FBRD).write_volatile(fbrd);
+            uart.reg::<u32>(IFLS).write_volatile(Self::ifls(fifo));
+            uart.reg::<u32>(LCR_H)
+                .write_volatile((lcr | LineControl::FEN).bits());
+
+            let mut cr = Control::UARTEN | Control::TXE | Control::RXE;
+            if config.flow_control {
+                cr |= Control::RTSEN | Control::CTSEN;
+            }
+            uart.reg::<u32>(CR).write_volatile(cr.bits());
+        }
+
+        Ok(1)
     }
 
     fn can_put(uart: UartData) -> bool {
-        const TXFF: u8 = 1 << 5;
-        unsafe { uart.reg_u8(0x18).read_volatile() & TXFF == 0 }
+        const TXFF: u32 = 1 << 5;
+        unsafe { uart.reg::<u32>(FR).read_volatile() & TXFF == 0 }
     }
 
     fn can_get(uart: UartData) -> bool {
-        const RXFE: u8 = 0x10;
-        unsafe { uart.reg_u8(0x18).read_volatile() & RXFE == 0 }
+        const RXFE: u32 = 0x10;
+        unsafe { uart.reg::<u32>(FR).read_volatile() & RXFE == 0 }
     }
 
     fn get_irq_event(uart: UartData) -> IrqEvent {
-        let mut event = IrqEvent::default();
-
         unsafe {
             let ris = uart.reg::<u32>(RIS).read_volatile();
             let mis = uart.reg::<u32>(MIS).read_volatile();
 
-            let sts = Interrupts::from_bits_retain(ris & mis);
-
-            if sts.contains(Interrupts::RXI) {
-                event.rx = true;
-            }
-
-            if sts.contains(Interrupts::TXI) {
-                event.tx = true;
-            }
+            Interrupts::from_bits_truncate(ris & mis).into()
         }
-        event
     }
 }
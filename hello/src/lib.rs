@@ -75,7 +75,8 @@ fn rust_entry(_text_va: usize, fdt: *mut u8) -> ! {
     clean_bss();
     enable_fp();
 
-    if let Some(mut uart) = any_uart::init(NonNull::new(fdt).unwrap(), phys_to_virt) {
+    let dtb = unsafe { any_uart::fdt_from_ptr(NonNull::new(fdt).unwrap()) }.unwrap();
+    if let Ok(mut uart) = any_uart::init(dtb, phys_to_virt) {
         let mut tx = uart.tx.take().unwrap();
         let _ = tx.write_str_blocking("Hello, world!\n");
 